@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use tokio::{
+    sync::mpsc,
+    time::{Instant, sleep_until},
+};
+
+/// Groups the items of an `mpsc` channel into batches.
+///
+/// A batch is emitted when it holds `max_items` items, or `max_latency` after
+/// `next_batch` received its first item, whichever comes first. An item that
+/// arrives exactly at the deadline goes into the next batch. Under load the
+/// batches are full. When the channel closes, the items that are left are
+/// emitted as a last, smaller batch.
+///
+/// The latency is counted from when the batcher takes an item out of the
+/// channel, not from when it was sent. Behind a slow consumer, items also
+/// wait in the channel, for as long as the consumer takes to call
+/// `next_batch` again.
+pub struct Batcher<T> {
+    rx: mpsc::Receiver<T>,
+    max_items: usize,
+    max_latency: Duration,
+    /// The batch being filled. It lives here rather than in `next_batch`, so
+    /// cancelling `next_batch` loses nothing.
+    batch: Vec<T>,
+    /// When the current batch is due; `None` while it is empty.
+    deadline: Option<Instant>,
+}
+
+impl<T> Batcher<T> {
+    pub fn new(
+        rx: mpsc::Receiver<T>,
+        max_items: usize,
+        max_latency: Duration,
+    ) -> Self {
+        assert!(max_items > 0, "max_items must be at least 1");
+        Self {
+            rx,
+            max_items,
+            max_latency,
+            batch: Vec::with_capacity(max_items),
+            deadline: None,
+        }
+    }
+
+    /// Waits for the next batch. Returns `None` once the channel is closed
+    /// and every item has been emitted.
+    ///
+    /// This method is cancel safe: items received by a call that is
+    /// cancelled, such as a losing branch of `select!`, are part of the batch
+    /// returned by the next call, which is still due at the same time.
+    pub async fn next_batch(&mut self) -> Option<Vec<T>> {
+        loop {
+            let limit = self.max_items - self.batch.len();
+            let received = match self.deadline {
+                None => self.rx.recv_many(&mut self.batch, limit).await,
+                // Deadline first: when an item is ready at the deadline too,
+                // the batch still goes out on time, without it.
+                Some(deadline) => tokio::select! {
+                    biased;
+                    _ = sleep_until(deadline) => return Some(self.take()),
+                    n = self.rx.recv_many(&mut self.batch, limit) => n,
+                },
+            };
+            if received == 0 {
+                // `recv_many` only returns 0 once the channel is closed and
+                // empty: flush what is left.
+                return (!self.batch.is_empty()).then(|| self.take());
+            }
+            if self.deadline.is_none() {
+                self.deadline = Some(Instant::now() + self.max_latency);
+            }
+            if self.batch.len() == self.max_items {
+                return Some(self.take());
+            }
+        }
+    }
+
+    fn take(&mut self) -> Vec<T> {
+        self.deadline = None;
+        std::mem::replace(&mut self.batch, Vec::with_capacity(self.max_items))
+    }
+}
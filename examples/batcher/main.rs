@@ -0,0 +1,178 @@
+mod batcher;
+
+use std::time::Duration;
+
+use tokio::{
+    sync::mpsc,
+    task,
+    time::{Instant, sleep, sleep_until, timeout},
+};
+
+use batcher::Batcher;
+
+const LATENCY: Duration = Duration::from_millis(50);
+
+/// A channel that receives each value at the given number of milliseconds
+/// after `start`, and closes after the last one.
+fn send_at<T>(start: Instant, events: Vec<(u64, T)>) -> mpsc::Receiver<T>
+where
+    T: Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        for (at, value) in events {
+            sleep_until(start + Duration::from_millis(at)).await;
+            tx.send(value).await.unwrap();
+        }
+    });
+    rx
+}
+
+/// Every batch, with the millisecond it was emitted at.
+async fn batches<T>(
+    start: Instant,
+    mut batcher: Batcher<T>,
+) -> Vec<(u64, Vec<T>)> {
+    let mut batches = Vec::new();
+    while let Some(batch) = batcher.next_batch().await {
+        batches.push((start.elapsed().as_millis() as u64, batch));
+    }
+    // Closed for good.
+    assert!(batcher.next_batch().await.is_none());
+    batches
+}
+
+async fn size_boundary() {
+    let start = Instant::now();
+    let (tx, rx) = mpsc::channel(16);
+    for i in 0..10 {
+        tx.send(i).await.unwrap();
+    }
+    let producer = tokio::spawn(async move {
+        sleep(Duration::from_millis(200)).await;
+        drop(tx);
+    });
+    let got = batches(start, Batcher::new(rx, 4, LATENCY)).await;
+    assert_eq!(
+        got,
+        [
+            // Full batches go out at once.
+            (0, vec![0, 1, 2, 3]),
+            (0, vec![4, 5, 6, 7]),
+            // The rest waits for the latency.
+            (50, vec![8, 9]),
+        ]
+    );
+    producer.await.unwrap();
+}
+
+async fn time_boundary() {
+    let start = Instant::now();
+    let events = vec![(0, 'a'), (10, 'b'), (49, 'c'), (51, 'd'), (90, 'e')];
+    let rx = send_at(start, [events, vec![(130, 'f')]].concat());
+    let got = batches(start, Batcher::new(rx, 100, LATENCY)).await;
+    assert_eq!(
+        got,
+        [
+            // Due 50 ms after `a`, just before `d` arrives.
+            (50, vec!['a', 'b', 'c']),
+            // Due 50 ms after `d`, not after the previous batch.
+            (101, vec!['d', 'e']),
+            // Flushed when the channel closes, before it is due.
+            (130, vec!['f']),
+        ]
+    );
+}
+
+async fn item_at_deadline() {
+    let start = Instant::now();
+    let rx = send_at(start, vec![(0, 'a'), (50, 'b'), (60, 'c'), (100, 'd')]);
+    let got = batches(start, Batcher::new(rx, 100, LATENCY)).await;
+    // `b` and `d` arrive exactly when the batch before them is due, and
+    // start the next one.
+    assert_eq!(
+        got,
+        [(50, vec!['a']), (100, vec!['b', 'c']), (100, vec!['d'])]
+    );
+}
+
+async fn latency_starts_at_first_item() {
+    let start = Instant::now();
+    let rx = send_at(start, vec![(30, 1), (75, 2), (85, 3), (200, 4)]);
+    let got = batches(start, Batcher::new(rx, 100, LATENCY)).await;
+    assert_eq!(got, [(80, vec![1, 2]), (135, vec![3]), (200, vec![4])]);
+}
+
+async fn flush_on_close() {
+    let (tx, rx) = mpsc::channel(16);
+    for i in 0..3 {
+        tx.send(i).await.unwrap();
+    }
+    drop(tx);
+    let mut batcher = Batcher::new(rx, 100, Duration::from_secs(3600));
+    let start = Instant::now();
+    assert_eq!(batcher.next_batch().await, Some(vec![0, 1, 2]));
+    assert_eq!(start.elapsed(), Duration::ZERO);
+    assert_eq!(batcher.next_batch().await, None);
+    assert_eq!(batcher.next_batch().await, None);
+}
+
+async fn cancel_safety() {
+    let start = Instant::now();
+    let rx = send_at(start, vec![(0, 1), (5, 2), (20, 3), (100, 4)]);
+    let mut batcher = Batcher::new(rx, 100, LATENCY);
+    // Give up on the batch after 10 ms: 1 and 2 were received by then.
+    assert!(
+        timeout(Duration::from_millis(10), batcher.next_batch())
+            .await
+            .is_err()
+    );
+    // They are still there, and the batch is still due at 50 ms.
+    let got = batches(start, batcher).await;
+    assert_eq!(got, [(50, vec![1, 2, 3]), (100, vec![4])]);
+}
+
+fn fib(n: usize) -> usize {
+    match n {
+        0 => 0,
+        1 => 1,
+        _ => fib(n - 1) + fib(n - 2),
+    }
+}
+
+/// `concurrency-primitives-mpsc.rs`, with the results handled in batches.
+async fn fib_batches() {
+    let (tx, rx) = mpsc::channel(16);
+    for i in 0..30 {
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let v = task::spawn_blocking(move || fib(i)).await.unwrap();
+            tx.send((i, v)).await.unwrap();
+        });
+    }
+    drop(tx);
+
+    let mut batcher = Batcher::new(rx, 8, LATENCY);
+    let mut seen = Vec::new();
+    while let Some(batch) = batcher.next_batch().await {
+        assert!(!batch.is_empty() && batch.len() <= 8, "{:?}", batch);
+        println!("batch of {}: {:?}", batch.len(), batch);
+        seen.extend(batch);
+    }
+    seen.sort();
+    let expected: Vec<_> = (0..30).map(|i| (i, fib(i))).collect();
+    assert_eq!(seen, expected);
+}
+
+// With the clock paused, Tokio jumps to the next timer whenever every task is
+// waiting, so the batch boundaries above are exact to the millisecond.
+#[tokio::main(flavor = "current_thread", start_paused = true)]
+async fn main() {
+    size_boundary().await;
+    time_boundary().await;
+    item_at_deadline().await;
+    latency_starts_at_first_item().await;
+    flush_on_close().await;
+    cancel_safety().await;
+    fib_batches().await;
+}
@@ -0,0 +1,119 @@
+use std::{
+    fmt,
+    panic::Location,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use tokio::task::JoinHandle;
+
+/// One `poll` call that took longer than the threshold.
+#[derive(Debug, Clone)]
+pub struct SlowPoll {
+    pub task: String,
+    /// Where the task was spawned.
+    pub location: &'static Location<'static>,
+    /// Which poll of the task it was, starting at 1.
+    pub poll: usize,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for SlowPoll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "task `{}` (spawned at {}) blocked the thread for {:?} in poll #{}",
+            self.task, self.location, self.elapsed, self.poll
+        )
+    }
+}
+
+/// Measures every `poll` of the tasks it spawns, and reports the ones that
+/// run longer than `threshold` without yielding. Such a poll holds up every
+/// other task on the same worker thread: it is almost always blocking or
+/// CPU-heavy code that belongs in `spawn_blocking`.
+#[derive(Debug, Clone)]
+pub struct Detector {
+    threshold: Duration,
+    slow: Arc<Mutex<Vec<SlowPoll>>>,
+}
+
+impl Detector {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            slow: Arc::default(),
+        }
+    }
+
+    /// Wraps `future` so each of its polls is timed. `#[track_caller]`
+    /// makes `Location::caller()` the line that called `instrument`.
+    #[track_caller]
+    pub fn instrument<F: Future>(
+        &self,
+        name: &str,
+        future: F,
+    ) -> Instrumented<F> {
+        Instrumented {
+            inner: Box::pin(future),
+            detector: self.clone(),
+            task: name.to_string(),
+            location: Location::caller(),
+            polls: 0,
+        }
+    }
+
+    /// `tokio::spawn`, with every poll of the task timed.
+    #[track_caller]
+    pub fn spawn<F>(&self, name: &str, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        tokio::spawn(self.instrument(name, future))
+    }
+
+    /// Every slow poll so far.
+    pub fn slow_polls(&self) -> Vec<SlowPoll> {
+        self.slow.lock().unwrap().clone()
+    }
+
+    fn report(&self, slow: SlowPoll) {
+        eprintln!("{} {}", "warning:".yellow().bold(), slow);
+        self.slow.lock().unwrap().push(slow);
+    }
+}
+
+pub struct Instrumented<F> {
+    inner: Pin<Box<F>>,
+    detector: Detector,
+    task: String,
+    location: &'static Location<'static>,
+    polls: usize,
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        self.polls += 1;
+        let start = Instant::now();
+        let result = self.inner.as_mut().poll(cx);
+        let elapsed = start.elapsed();
+        // The warning can only come after the poll returns: while it runs,
+        // nothing else gets the thread. A poll that never returns needs a
+        // watchdog on another thread to be noticed.
+        if elapsed > self.detector.threshold {
+            self.detector.report(SlowPoll {
+                task: self.task.clone(),
+                location: self.location,
+                poll: self.polls,
+                elapsed,
+            });
+        }
+        result
+    }
+}
@@ -0,0 +1,90 @@
+mod detector;
+
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+use tokio::{task, time::sleep};
+
+use detector::Detector;
+
+const THRESHOLD: Duration = Duration::from_millis(10);
+
+fn fib(n: usize) -> usize {
+    match n {
+        0 => 0,
+        1 => 1,
+        _ => fib(n - 1) + fib(n - 2),
+    }
+}
+
+/// Ticks every 5 ms, and returns the longest gap between two ticks: how
+/// long the thread was not available to it.
+async fn heartbeat(ticks: usize) -> Duration {
+    let mut longest = Duration::ZERO;
+    let mut last = Instant::now();
+    for _ in 0..ticks {
+        sleep(Duration::from_millis(5)).await;
+        longest = longest.max(last.elapsed());
+        last = Instant::now();
+    }
+    longest
+}
+
+// A single thread makes the damage easy to see: a blocked poll stops every
+// other task. On a multi-threaded runtime it stops the tasks queued on the
+// same worker.
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let detector = Detector::new(THRESHOLD);
+
+    // Done right, as in `concurrency-primitives-mpsc.rs`: the work runs on
+    // a blocking thread, and the task only waits for it.
+    let beat = detector.spawn("heartbeat", heartbeat(40));
+    let good = detector.spawn("fib-blocking", async {
+        task::spawn_blocking(|| fib(35)).await.unwrap()
+    });
+    let good = good.await?;
+    let longest = beat.await?;
+    assert!(detector.slow_polls().is_empty());
+    println!(
+        "{} fib(35) = {} on a blocking thread, heartbeat gap at most {:?}",
+        "ok:".green(),
+        good,
+        longest
+    );
+
+    // Forgot `spawn_blocking`: the whole computation happens inside one
+    // poll, and the heartbeat stops meanwhile.
+    let beat = detector.spawn("heartbeat", heartbeat(40));
+    let line = line!() + 1;
+    let bad = detector.spawn("fib-inline", async { fib(35) });
+    assert_eq!(bad.await?, good);
+    let longest = beat.await?;
+
+    let slow = detector.slow_polls();
+    assert_eq!(slow.len(), 1, "{:?}", slow);
+    assert_eq!(slow[0].task, "fib-inline");
+    assert_eq!(slow[0].poll, 1);
+    assert!(slow[0].location.file().ends_with("main.rs"));
+    assert_eq!(slow[0].location.line(), line);
+    // The heartbeat was held up for as long as the slow poll took.
+    assert!(longest >= slow[0].elapsed, "{:?}", longest);
+    println!(
+        "{} heartbeat gap of {:?} while fib(35) ran inline",
+        "detected:".red(),
+        longest
+    );
+
+    // Many short polls are fine, however long the task runs in total.
+    let detector = Detector::new(THRESHOLD);
+    detector
+        .spawn("chunks", async {
+            for n in 0..200 {
+                fib(n % 20);
+                task::yield_now().await;
+            }
+        })
+        .await?;
+    assert!(detector.slow_polls().is_empty());
+    Ok(())
+}
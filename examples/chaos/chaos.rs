@@ -0,0 +1,239 @@
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use tokio::{task::JoinHandle, time::sleep};
+
+use crate::chaos_io::ChaosIo;
+
+/// Probabilities are per read or write call.
+#[derive(Debug, Clone, Copy)]
+pub struct IoFaults {
+    pub delay: f64,
+    pub max_delay: Duration,
+    /// Reads or writes only part of the buffer.
+    pub short: f64,
+    pub error: f64,
+    pub error_kind: io::ErrorKind,
+}
+
+/// Probabilities are per spawned task.
+#[derive(Debug, Clone, Copy)]
+struct TaskFaults {
+    panic: f64,
+    stall: f64,
+    stall_for: Duration,
+}
+
+/// How many faults were injected so far.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub delays: AtomicUsize,
+    pub shorts: AtomicUsize,
+    pub errors: AtomicUsize,
+    pub panics: AtomicUsize,
+    pub stalls: AtomicUsize,
+}
+
+impl Stats {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} delays, {} short, {} errors, {} panics, {} stalls",
+            self.delays.load(Ordering::Relaxed),
+            self.shorts.load(Ordering::Relaxed),
+            self.errors.load(Ordering::Relaxed),
+            self.panics.load(Ordering::Relaxed),
+            self.stalls.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Configures a [`Chaos`]. Every fault is off unless enabled.
+#[derive(Debug)]
+pub struct ChaosBuilder {
+    seed: Option<u64>,
+    io: IoFaults,
+    task: TaskFaults,
+}
+
+impl ChaosBuilder {
+    /// Without a seed, a random one is picked. Either way `Chaos::seed`
+    /// returns it, so a failing run can be repeated exactly.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Delays a read or write by up to `max`.
+    pub fn io_delay(mut self, probability: f64, max: Duration) -> Self {
+        self.io.delay = probability;
+        self.io.max_delay = max;
+        self
+    }
+
+    pub fn io_short(mut self, probability: f64) -> Self {
+        self.io.short = probability;
+        self
+    }
+
+    pub fn io_error(mut self, probability: f64, kind: io::ErrorKind) -> Self {
+        self.io.error = probability;
+        self.io.error_kind = kind;
+        self
+    }
+
+    /// The task panics on one of its first three polls, unless it finished
+    /// before that: possibly before it starts, possibly half way through its
+    /// work. Which poll is drawn from the RNG, not from how long anything
+    /// takes, so the same seed always gives the same panics.
+    pub fn task_panic(mut self, probability: f64) -> Self {
+        self.task.panic = probability;
+        self
+    }
+
+    /// The task sleeps for `duration` before it starts.
+    pub fn task_stall(mut self, probability: f64, duration: Duration) -> Self {
+        self.task.stall = probability;
+        self.task.stall_for = duration;
+        self
+    }
+
+    pub fn build(self) -> Chaos {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        Chaos {
+            seed,
+            rng: Arc::new(Mutex::new(StdRng::seed_from_u64(seed))),
+            io: self.io,
+            task: self.task,
+            stats: Arc::new(Stats::default()),
+        }
+    }
+}
+
+/// Injects faults into I/O objects and tasks, driven by a seeded RNG.
+///
+/// Each wrapped object and spawned task draws its own RNG from the main
+/// one, so a run is reproducible from the seed as long as objects are
+/// wrapped and tasks spawned in the same order.
+#[derive(Debug, Clone)]
+pub struct Chaos {
+    seed: u64,
+    rng: Arc<Mutex<StdRng>>,
+    io: IoFaults,
+    task: TaskFaults,
+    stats: Arc<Stats>,
+}
+
+impl Chaos {
+    pub fn builder() -> ChaosBuilder {
+        ChaosBuilder {
+            seed: None,
+            io: IoFaults {
+                delay: 0.0,
+                max_delay: Duration::ZERO,
+                short: 0.0,
+                error: 0.0,
+                error_kind: io::ErrorKind::Other,
+            },
+            task: TaskFaults {
+                panic: 0.0,
+                stall: 0.0,
+                stall_for: Duration::ZERO,
+            },
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn fork_rng(&self) -> StdRng {
+        let seed = self.rng.lock().unwrap().random();
+        StdRng::seed_from_u64(seed)
+    }
+
+    /// A `Chaos` with the same settings and statistics, but its own RNG.
+    /// Hand one to each concurrent task, so the faults one task sees do not
+    /// depend on how the tasks happen to be scheduled.
+    pub fn fork(&self) -> Chaos {
+        Chaos {
+            rng: Arc::new(Mutex::new(self.fork_rng())),
+            ..self.clone()
+        }
+    }
+
+    /// Wraps an `AsyncRead` and/or `AsyncWrite` in a faulty one.
+    pub fn wrap<T>(&self, inner: T) -> ChaosIo<T> {
+        ChaosIo::new(inner, self.io, self.fork_rng(), self.stats.clone())
+    }
+
+    /// Like `tokio::spawn`, but the task may panic or stall.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let mut rng = self.fork_rng();
+        let task = self.task;
+        let stats = self.stats.clone();
+        let panic_at = if rng.random_bool(task.panic) {
+            Some(rng.random_range(1..=3))
+        } else {
+            None
+        };
+        let stall = rng.random_bool(task.stall);
+        if stall {
+            stats.stalls.fetch_add(1, Ordering::Relaxed);
+        }
+
+        tokio::spawn(async move {
+            if stall {
+                sleep(task.stall_for).await;
+            }
+            match panic_at {
+                None => future.await,
+                Some(poll) => {
+                    PanicAt {
+                        inner: Box::pin(future),
+                        polls_left: poll,
+                        stats,
+                    }
+                    .await
+                }
+            }
+        })
+    }
+}
+
+/// Panics on a given poll. The panic only happens if the inner future is
+/// still running by then, just like a real bug on a rarely taken path.
+struct PanicAt<F> {
+    inner: Pin<Box<F>>,
+    polls_left: u32,
+    stats: Arc<Stats>,
+}
+
+impl<F: Future> Future for PanicAt<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        self.polls_left -= 1;
+        if self.polls_left == 0 {
+            self.stats.panics.fetch_add(1, Ordering::Relaxed);
+            panic!("chaos: injected panic");
+        }
+        self.inner.as_mut().poll(cx)
+    }
+}
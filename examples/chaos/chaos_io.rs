@@ -0,0 +1,169 @@
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, atomic::Ordering},
+    task::{Context, Poll, ready},
+};
+
+use rand::{Rng, rngs::StdRng};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::{Sleep, sleep},
+};
+
+use crate::chaos::{IoFaults, Stats};
+
+/// What happens to one read or write call.
+#[derive(Debug)]
+enum Fault {
+    Pass,
+    Delay(Pin<Box<Sleep>>),
+    /// Only this fraction of the buffer is used, but at least one byte.
+    Short(f64),
+    Error(io::ErrorKind),
+}
+
+/// An I/O object that randomly delays, shortens or fails reads and writes.
+/// Created by `Chaos::wrap`.
+pub struct ChaosIo<T> {
+    inner: T,
+    faults: IoFaults,
+    rng: StdRng,
+    stats: Arc<Stats>,
+    // Decided once per call and kept until the call completes, so an inner
+    // object returning `Pending` does not change the outcome.
+    read_fault: Option<Fault>,
+    write_fault: Option<Fault>,
+}
+
+impl<T> ChaosIo<T> {
+    pub(crate) fn new(
+        inner: T,
+        faults: IoFaults,
+        rng: StdRng,
+        stats: Arc<Stats>,
+    ) -> Self {
+        Self {
+            inner,
+            faults,
+            rng,
+            stats,
+            read_fault: None,
+            write_fault: None,
+        }
+    }
+}
+
+/// Decides what goes wrong with the current call, if not decided yet, and
+/// waits out a delay.
+fn poll_fault(
+    slot: &mut Option<Fault>,
+    faults: &IoFaults,
+    rng: &mut StdRng,
+    stats: &Stats,
+    cx: &mut Context<'_>,
+) -> Poll<()> {
+    let fault = slot.get_or_insert_with(|| {
+        let roll: f64 = rng.random();
+        if roll < faults.error {
+            stats.errors.fetch_add(1, Ordering::Relaxed);
+            Fault::Error(faults.error_kind)
+        } else if roll < faults.error + faults.short {
+            stats.shorts.fetch_add(1, Ordering::Relaxed);
+            Fault::Short(rng.random())
+        } else if roll < faults.error + faults.short + faults.delay {
+            stats.delays.fetch_add(1, Ordering::Relaxed);
+            let delay = faults.max_delay.mul_f64(rng.random());
+            Fault::Delay(Box::pin(sleep(delay)))
+        } else {
+            Fault::Pass
+        }
+    });
+    if let Fault::Delay(delay) = fault {
+        ready!(delay.as_mut().poll(cx));
+        *fault = Fault::Pass;
+    }
+    Poll::Ready(())
+}
+
+fn shortened(len: usize, fraction: f64) -> usize {
+    ((len as f64 * fraction) as usize).clamp(len.min(1), len)
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for ChaosIo<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(poll_fault(
+            &mut this.read_fault,
+            &this.faults,
+            &mut this.rng,
+            &this.stats,
+            cx
+        ));
+        let result = match this.read_fault {
+            Some(Fault::Error(kind)) => Poll::Ready(Err(kind.into())),
+            Some(Fault::Short(fraction)) => {
+                let mut limited = vec![0; shortened(buf.remaining(), fraction)];
+                let mut limited_buf = ReadBuf::new(&mut limited);
+                let result =
+                    Pin::new(&mut this.inner).poll_read(cx, &mut limited_buf);
+                if let Poll::Ready(Ok(())) = result {
+                    buf.put_slice(limited_buf.filled());
+                }
+                result
+            }
+            _ => Pin::new(&mut this.inner).poll_read(cx, buf),
+        };
+        if result.is_ready() {
+            this.read_fault = None;
+        }
+        result
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for ChaosIo<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(poll_fault(
+            &mut this.write_fault,
+            &this.faults,
+            &mut this.rng,
+            &this.stats,
+            cx
+        ));
+        let result = match this.write_fault {
+            Some(Fault::Error(kind)) => Poll::Ready(Err(kind.into())),
+            Some(Fault::Short(fraction)) => {
+                let n = shortened(buf.len(), fraction);
+                Pin::new(&mut this.inner).poll_write(cx, &buf[..n])
+            }
+            _ => Pin::new(&mut this.inner).poll_write(cx, buf),
+        };
+        if result.is_ready() {
+            this.write_fault = None;
+        }
+        result
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
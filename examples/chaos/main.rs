@@ -0,0 +1,172 @@
+mod chaos;
+mod chaos_io;
+
+use std::time::Duration;
+
+use colored::Colorize;
+use tokio::{
+    io::{self, ErrorKind},
+    task::JoinSet,
+    time::{sleep, timeout},
+};
+
+use chaos::Chaos;
+
+const MAX_ATTEMPTS: usize = 20;
+
+/// Like `io::copy` from `io-copy.rs`, but survives transient errors: after
+/// a failure it starts over from the last byte that was actually written.
+async fn copy_with_retry(
+    chaos: &Chaos,
+    data: &[u8],
+) -> io::Result<(Vec<u8>, usize)> {
+    let mut out = Vec::new();
+    for attempt in 1..=MAX_ATTEMPTS {
+        let mut reader = chaos.wrap(&data[out.len()..]);
+        let mut writer = chaos.wrap(&mut out);
+        match io::copy(&mut reader, &mut writer).await {
+            Ok(_) => return Ok((out, attempt)),
+            Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Err(io::Error::other("too many attempts"))
+}
+
+async fn io_chaos(seed: u64) -> anyhow::Result<()> {
+    let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+    let trials = 20;
+    let mut naive_failures = 0;
+
+    for trial in 0..trials {
+        let chaos = Chaos::builder()
+            .seed(seed.wrapping_add(trial))
+            .io_delay(0.05, Duration::from_millis(2))
+            .io_short(0.3)
+            .io_error(0.01, ErrorKind::ConnectionReset)
+            .build();
+
+        // A plain `io::copy` copes with delays and short reads and writes,
+        // but gives up on the first error.
+        let mut out = Vec::new();
+        let copied =
+            io::copy(&mut chaos.wrap(&data[..]), &mut chaos.wrap(&mut out))
+                .await;
+        if copied.is_err() {
+            naive_failures += 1;
+        }
+
+        let (out, attempts) = copy_with_retry(&chaos, &data).await?;
+        assert!(out == data, "seed {}", chaos.seed());
+        println!(
+            "  seed {:>20}: {} attempt(s), {}",
+            chaos.seed(),
+            attempts,
+            chaos.stats().summary()
+        );
+    }
+    println!(
+        "{} plain io::copy failed {} of {} times, copy_with_retry never",
+        "I/O chaos:".green(),
+        naive_failures,
+        trials
+    );
+    Ok(())
+}
+
+async fn job(id: u64) -> u64 {
+    sleep(Duration::from_millis(10)).await;
+    id * id
+}
+
+/// What happened to each attempt of a job.
+type FaultLog = Vec<(u64, Vec<&'static str>)>;
+
+/// Like `task-management-task-failure.rs`, but instead of only reporting a
+/// failed task, restarts it. A stalled task is cut off by a timeout.
+async fn supervise(chaos: Chaos, id: u64) -> (u64, u64, Vec<&'static str>) {
+    let mut attempts = Vec::new();
+    for _ in 1..=MAX_ATTEMPTS {
+        let mut handle = chaos.spawn(job(id));
+        match timeout(Duration::from_millis(200), &mut handle).await {
+            Ok(Ok(result)) => {
+                attempts.push("ok");
+                return (id, result, attempts);
+            }
+            Ok(Err(err)) => {
+                assert!(err.is_panic());
+                attempts.push("panic");
+            }
+            Err(_elapsed) => {
+                handle.abort();
+                attempts.push("stall");
+            }
+        }
+    }
+    panic!(
+        "job {} failed {} times, seed {}",
+        id,
+        MAX_ATTEMPTS,
+        chaos.seed()
+    );
+}
+
+async fn task_chaos(seed: u64) -> anyhow::Result<FaultLog> {
+    let chaos = Chaos::builder()
+        .seed(seed)
+        .task_panic(0.3)
+        .task_stall(0.1, Duration::from_secs(3600))
+        .build();
+
+    // Every supervisor gets its own fork, so the faults depend only on the
+    // seed and not on the order in which the supervisors run.
+    let mut jobs = JoinSet::new();
+    for id in 0..10 {
+        jobs.spawn(supervise(chaos.fork(), id));
+    }
+    let mut restarts = 0;
+    let mut log = FaultLog::new();
+    while let Some(result) = jobs.join_next().await {
+        let (id, result, attempts) = result?;
+        assert_eq!(result, id * id, "seed {}", seed);
+        restarts += attempts.len() - 1;
+        log.push((id, attempts));
+    }
+    log.sort();
+    println!(
+        "{} 10 jobs finished after {} restarts ({})",
+        "task chaos:".green(),
+        restarts,
+        chaos.stats().summary()
+    );
+    Ok(log)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // `cargo run --example chaos -- <seed>` repeats a run.
+    let seed = match std::env::args().nth(1) {
+        Some(seed) => seed.parse()?,
+        None => rand::random(),
+    };
+    println!("chaos seed: {}", seed);
+
+    // Injected panics are expected; keep them out of the output.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if info.payload().downcast_ref::<&str>()
+            != Some(&"chaos: injected panic")
+        {
+            default_hook(info);
+        }
+    }));
+
+    io_chaos(seed).await?;
+
+    // The same seed injects the same faults into the same attempts, however
+    // the tasks happen to be scheduled.
+    let first = task_chaos(seed).await?;
+    let second = task_chaos(seed).await?;
+    assert_eq!(first, second, "seed {}", seed);
+    Ok(())
+}
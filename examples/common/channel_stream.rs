@@ -0,0 +1,65 @@
+//! An `mpsc` receiver as a `Stream`, and `next` to await a stream's items,
+//! without depending on `tokio-stream` or `futures`.
+#![allow(dead_code)] // not every example uses every helper
+
+use std::{
+    future::poll_fn,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use tokio::{sync::mpsc, task::JoinHandle};
+
+/// The values received on an `mpsc` channel, as a `Stream`. It ends once every
+/// `Sender` is dropped, as `recv` returns `None` then.
+///
+/// Often the channel is fed by a background task: writing the polling logic as
+/// a plain `async` loop in a task is much easier than implementing `poll_next`
+/// by hand. Such a task is aborted when the stream is dropped.
+pub struct ChannelStream<T> {
+    rx: mpsc::Receiver<T>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl<T> ChannelStream<T> {
+    pub fn new(rx: mpsc::Receiver<T>) -> Self {
+        Self { rx, task: None }
+    }
+
+    /// A stream fed by `task`, which is aborted when the stream is dropped.
+    pub fn with_task(rx: mpsc::Receiver<T>, task: JoinHandle<()>) -> Self {
+        Self {
+            rx,
+            task: Some(task),
+        }
+    }
+}
+
+impl<T> Stream for ChannelStream<T> {
+    type Item = T;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<T>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl<T> Drop for ChannelStream<T> {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+/// Awaits the next item of any `Unpin` stream; a stand-in for
+/// `StreamExt::next` from the `futures` crate.
+pub async fn next<S>(stream: &mut S) -> Option<S::Item>
+where
+    S: Stream + Unpin + ?Sized,
+{
+    poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
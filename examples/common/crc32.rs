@@ -0,0 +1,40 @@
+//! CRC-32 (IEEE), the checksum of zip, gzip and PNG, for the examples that
+//! need to detect corrupted data.
+//!
+//! It is computed bit by bit: slow, but short and dependency free. Use a crate
+//! such as `crc32fast` for anything real.
+#![allow(dead_code)] // not every example uses every helper
+
+/// An incremental CRC-32: feeding the data in pieces gives the same result
+/// as `crc32` over all of it.
+#[derive(Debug, Clone)]
+pub struct Crc32 {
+    crc: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self { crc: !0 }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.crc ^= byte as u32;
+            for _ in 0..8 {
+                let mask = (self.crc & 1).wrapping_neg();
+                self.crc = (self.crc >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+    }
+
+    /// The checksum of everything fed so far. Does not reset the state.
+    pub fn value(&self) -> u32 {
+        !self.crc
+    }
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.value()
+}
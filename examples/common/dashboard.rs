@@ -0,0 +1,319 @@
+//! A terminal dashboard of runtime and task metrics, for any example.
+//!
+//! Spawn tasks through `Metrics` to get per-task counters, then start a
+//! `Dashboard` next to the example's own work. Runtime metrics come from
+//! `Handle::metrics()`; the per-worker and blocking-pool numbers are only
+//! available when built with `RUSTFLAGS="--cfg tokio_unstable"`.
+#![allow(dead_code)] // not every example uses every helper
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{IsTerminal, Write as _},
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use tokio::{
+    runtime::Handle,
+    sync::oneshot,
+    task::{self, JoinHandle},
+    time::{MissedTickBehavior, interval},
+};
+
+/// Counters for one kind of task, by name.
+#[derive(Debug, Default)]
+struct Counters {
+    spawned: AtomicU64,
+    completed: AtomicU64,
+    polls: AtomicU64,
+    busy_nanos: AtomicU64,
+    blocking: bool,
+}
+
+/// A snapshot of the counters of one kind of task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskStats {
+    pub name: String,
+    pub blocking: bool,
+    pub spawned: u64,
+    pub completed: u64,
+    /// For blocking tasks, one poll per completed call.
+    pub polls: u64,
+    /// Time spent inside `poll`, or running the closure.
+    pub busy: Duration,
+}
+
+impl TaskStats {
+    pub fn running(&self) -> u64 {
+        self.spawned - self.completed
+    }
+}
+
+/// Spawns tasks and counts what they do.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    tasks: Mutex<BTreeMap<String, Arc<Counters>>>,
+}
+
+impl Metrics {
+    pub fn new() -> Arc<Self> {
+        Arc::default()
+    }
+
+    fn counters(&self, name: &str, blocking: bool) -> Arc<Counters> {
+        let mut tasks = self.tasks.lock().unwrap();
+        let counters = tasks.entry(name.to_string()).or_insert_with(|| {
+            Arc::new(Counters {
+                blocking,
+                ..Counters::default()
+            })
+        });
+        counters.spawned.fetch_add(1, Ordering::Relaxed);
+        counters.clone()
+    }
+
+    /// `tokio::spawn`, counting the task's polls and the time spent in them.
+    pub fn spawn<F>(&self, name: &str, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        tokio::spawn(Counted {
+            inner: Box::pin(future),
+            counters: self.counters(name, false),
+        })
+    }
+
+    /// `spawn_blocking`, counting the calls and the time they take.
+    pub fn spawn_blocking<F, R>(&self, name: &str, f: F) -> JoinHandle<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let counters = self.counters(name, true);
+        task::spawn_blocking(move || {
+            let start = Instant::now();
+            let result = f();
+            counters.record_poll(start.elapsed());
+            counters.completed.fetch_add(1, Ordering::Relaxed);
+            result
+        })
+    }
+
+    pub fn snapshot(&self) -> Vec<TaskStats> {
+        let tasks = self.tasks.lock().unwrap();
+        tasks
+            .iter()
+            .map(|(name, counters)| TaskStats {
+                name: name.clone(),
+                blocking: counters.blocking,
+                spawned: counters.spawned.load(Ordering::Relaxed),
+                completed: counters.completed.load(Ordering::Relaxed),
+                polls: counters.polls.load(Ordering::Relaxed),
+                busy: Duration::from_nanos(
+                    counters.busy_nanos.load(Ordering::Relaxed),
+                ),
+            })
+            .collect()
+    }
+}
+
+impl Counters {
+    fn record_poll(&self, elapsed: Duration) {
+        self.polls.fetch_add(1, Ordering::Relaxed);
+        self.busy_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+struct Counted<F> {
+    inner: Pin<Box<F>>,
+    counters: Arc<Counters>,
+}
+
+impl<F: Future> Future for Counted<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let start = Instant::now();
+        let result = self.inner.as_mut().poll(cx);
+        self.counters.record_poll(start.elapsed());
+        if result.is_ready() {
+            self.counters.completed.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+/// Renders runtime and task metrics.
+pub struct Dashboard {
+    handle: Handle,
+    metrics: Arc<Metrics>,
+    started: Instant,
+}
+
+/// The running dashboard; `stop` ends it.
+pub struct DashboardHandle {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl DashboardHandle {
+    /// Stops refreshing, after drawing one last frame.
+    pub async fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.task.await;
+    }
+}
+
+impl Dashboard {
+    /// A dashboard for the current runtime.
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self {
+            handle: Handle::current(),
+            metrics,
+            started: Instant::now(),
+        }
+    }
+
+    /// Redraws the dashboard every `every`. On a terminal the frame is
+    /// drawn in place; otherwise, such as when piped to a file, frames are
+    /// printed one after another.
+    pub fn start(self, every: Duration) -> DashboardHandle {
+        let (stop, mut stopped) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let mut ticker = interval(every);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => self.draw(),
+                    _ = &mut stopped => break,
+                }
+            }
+            self.draw();
+        });
+        DashboardHandle { stop, task }
+    }
+
+    fn draw(&self) {
+        let frame = self.render();
+        let mut stdout = std::io::stdout().lock();
+        if stdout.is_terminal() {
+            // Move to the top left corner and clear the screen.
+            let _ = write!(stdout, "\x1b[H\x1b[2J");
+        }
+        let _ = writeln!(stdout, "{}", frame);
+        let _ = stdout.flush();
+    }
+
+    /// One frame of the dashboard, as text.
+    pub fn render(&self) -> String {
+        let metrics = self.handle.metrics();
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{} {:>8.1?}",
+            "tokio dashboard".bold().cyan(),
+            self.started.elapsed()
+        );
+        let _ = writeln!(
+            out,
+            "workers {:>3}   alive tasks {:>5}   global queue {}",
+            metrics.num_workers(),
+            metrics.num_alive_tasks(),
+            level(metrics.global_queue_depth() as u64, 10, 100)
+        );
+        render_unstable(&mut out, &self.handle);
+
+        let _ = writeln!(
+            out,
+            "{}",
+            format!(
+                "{:<16} {:>5} {:>8} {:>8} {:>9} {:>10} {:>10}",
+                "task", "kind", "running", "done", "polls", "busy", "mean poll"
+            )
+            .bold()
+        );
+        for task in self.metrics.snapshot() {
+            let mean =
+                task.busy.checked_div(task.polls as u32).unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "{:<16} {:>5} {:>8} {:>8} {:>9} {:>10.1?} {:>10}",
+                task.name,
+                if task.blocking { "block" } else { "async" },
+                task.running(),
+                task.completed,
+                task.polls,
+                task.busy,
+                poll_time(mean, task.blocking),
+            );
+        }
+        out
+    }
+}
+
+/// A number colored by how worrying it is.
+fn level(value: u64, warn: u64, bad: u64) -> String {
+    let text = value.to_string();
+    match value {
+        v if v >= bad => text.red().to_string(),
+        v if v >= warn => text.yellow().to_string(),
+        _ => text.green().to_string(),
+    }
+}
+
+/// Mean poll time, in red when an async task holds the thread too long.
+/// Blocking tasks are expected to take long.
+fn poll_time(mean: Duration, blocking: bool) -> String {
+    let text = format!("{:>10.1?}", mean);
+    if blocking {
+        text.dimmed().to_string()
+    } else if mean > Duration::from_millis(10) {
+        text.red().to_string()
+    } else if mean > Duration::from_millis(1) {
+        text.yellow().to_string()
+    } else {
+        text.green().to_string()
+    }
+}
+
+#[cfg(tokio_unstable)]
+fn render_unstable(out: &mut String, handle: &Handle) {
+    let metrics = handle.metrics();
+    let _ = writeln!(
+        out,
+        "blocking threads {:>3} (idle {:>3})   blocking queue {}",
+        metrics.num_blocking_threads(),
+        metrics.num_idle_blocking_threads(),
+        level(metrics.blocking_queue_depth() as u64, 10, 100)
+    );
+    for worker in 0..metrics.num_workers() {
+        let _ = writeln!(
+            out,
+            "  worker {:>2}: queue {:>4}  polls {:>8}  steals {:>6}  busy {:>8.1?}",
+            worker,
+            level(metrics.worker_local_queue_depth(worker) as u64, 32, 128),
+            metrics.worker_poll_count(worker),
+            metrics.worker_steal_count(worker),
+            metrics.worker_total_busy_duration(worker),
+        );
+    }
+}
+
+#[cfg(not(tokio_unstable))]
+fn render_unstable(out: &mut String, _handle: &Handle) {
+    let _ = writeln!(
+        out,
+        "{}",
+        "(build with RUSTFLAGS=\"--cfg tokio_unstable\" for per-worker metrics)"
+            .dimmed()
+    );
+}
@@ -0,0 +1,126 @@
+//! Temporary files and directories for the examples.
+//!
+//! Every fixture gets a unique name, so several examples can run at the same
+//! time without clobbering each other's files, and is removed when dropped,
+//! even if the example bails out early with `?` or panics.
+#![allow(dead_code)] // not every example uses every helper
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// `<system temp dir>/<prefix>-<pid>-<counter>-<random>`
+fn unique_path(prefix: &str) -> PathBuf {
+    let name = format!(
+        "{}-{}-{}-{:08x}",
+        prefix,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed),
+        rand::random::<u32>(),
+    );
+    std::env::temp_dir().join(name)
+}
+
+/// A directory that is removed, with everything in it, when dropped.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub async fn new(prefix: &str) -> io::Result<Self> {
+        loop {
+            let path = unique_path(prefix);
+            // `create_dir` (not `create_dir_all`) fails if the path exists, so
+            // we never share a directory with anyone else.
+            match tokio::fs::create_dir(&path).await {
+                Ok(()) => return Ok(Self { path }),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of an entry inside the directory. Nothing is created.
+    pub fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.path.join(name)
+    }
+
+    /// Removes the directory now, reporting any error instead of ignoring it
+    /// like `Drop` has to.
+    pub async fn close(mut self) -> io::Result<()> {
+        let path = std::mem::take(&mut self.path);
+        tokio::fs::remove_dir_all(path).await
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        // `Drop` cannot `.await`, so this uses the blocking `std::fs`. For
+        // the small fixtures of an example that is perfectly fine.
+        if !self.path.as_os_str().is_empty() {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// A file that is removed when dropped.
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    /// Creates an empty file.
+    pub async fn new(prefix: &str) -> io::Result<Self> {
+        Self::with_contents(prefix, b"").await
+    }
+
+    pub async fn with_contents(
+        prefix: &str,
+        contents: impl AsRef<[u8]>,
+    ) -> io::Result<Self> {
+        loop {
+            let path = unique_path(prefix);
+            let created = tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .await;
+            match created {
+                Ok(_) => {
+                    // From here on `Drop` cleans up, even if the write fails.
+                    let file = Self { path };
+                    tokio::fs::write(&file.path, contents).await?;
+                    return Ok(file);
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempFile {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
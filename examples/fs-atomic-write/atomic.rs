@@ -0,0 +1,79 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+/// Distinguishes temp files of concurrent writers within this process.
+static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "path has no file name")
+    })?;
+    let unique = NEXT_TEMP.fetch_add(1, Ordering::Relaxed);
+    let temp_name = format!(
+        ".{}.tmp-{}-{}",
+        name.to_string_lossy(),
+        std::process::id(),
+        unique
+    );
+    Ok(path.with_file_name(temp_name))
+}
+
+/// Replaces the contents of `path` so that, even if we crash at any point,
+/// the file holds either the complete old or the complete new contents.
+///
+/// 1. Write the new contents to a temp file in the *same directory* (a rename
+///    is only atomic within one file system).
+/// 2. `flush` and `sync_all` the temp file, so its data is on disk before it becomes
+///    visible under the final name.
+/// 3. Rename it over `path`, which atomically swaps the directory entry.
+/// 4. `sync_all` the directory, so the rename itself survives a power loss.
+pub async fn atomic_write(
+    path: impl AsRef<Path>,
+    contents: impl AsRef<[u8]>,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let temp = temp_path(path)?;
+
+    let result = async {
+        // `create_new` fails instead of clobbering an existing file.
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+            .await?;
+        file.write_all(contents.as_ref()).await?;
+        // Tokio's `File` finishes writes in the background; `flush` waits
+        // for them and reports their errors, which `sync_all` would not.
+        file.flush().await?;
+        file.sync_all().await?;
+        drop(file);
+
+        fs::rename(&temp, path).await?;
+        sync_dir(path).await
+    }
+    .await;
+
+    if result.is_err() {
+        // Best effort: don't leave a half written temp file behind.
+        let _ = fs::remove_file(&temp).await;
+    }
+    result
+}
+
+/// Flushes the directory containing `path`, making renames and newly created
+/// entries in it durable.
+pub async fn sync_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(dir).await?.sync_all().await
+}
@@ -0,0 +1,147 @@
+use std::{io, path::Path};
+
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use crate::{atomic::sync_dir, crc32::crc32};
+
+/// Every record is stored as `[len: u32][crc32: u32][payload]`, both numbers
+/// big-endian. The checksum covers the payload, which is never empty.
+const HEADER_LEN: usize = 8;
+
+/// The length field for a payload of `len` bytes. Fails from 4 GiB on, which
+/// `as u32` would silently cut down to a wrong length.
+pub fn length_field(len: usize) -> io::Result<u32> {
+    u32::try_from(len).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, "WAL record too large")
+    })
+}
+
+pub fn encode(payload: &[u8]) -> io::Result<Vec<u8>> {
+    if payload.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "WAL records must not be empty",
+        ));
+    }
+    let len = length_field(payload.len())?;
+    let mut record = Vec::with_capacity(HEADER_LEN + payload.len());
+    record.extend_from_slice(&len.to_be_bytes());
+    record.extend_from_slice(&crc32(payload).to_be_bytes());
+    record.extend_from_slice(payload);
+    Ok(record)
+}
+
+/// Decodes records from the start of `log` until the first incomplete or
+/// corrupt one. Returns the records and the length of the valid prefix.
+pub fn decode(log: &[u8]) -> (Vec<Vec<u8>>, usize) {
+    let mut records = Vec::new();
+    let mut offset = 0;
+
+    while let Some(header) = log.get(offset..offset + HEADER_LEN) {
+        let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let crc = u32::from_be_bytes(header[4..].try_into().unwrap());
+        // `crc32(&[]) == 0`, so an all-zero header would pass as an empty
+        // record. Zeros are what a crash often leaves after the file has
+        // grown, so empty records are not allowed.
+        if len == 0 {
+            break;
+        }
+        let start = offset + HEADER_LEN;
+        // A torn write leaves a record whose payload is cut short...
+        let Some(payload) = log.get(start..start + len) else {
+            break;
+        };
+        // ...or whose bytes never made it to disk correctly.
+        if crc32(payload) != crc {
+            break;
+        }
+        records.push(payload.to_vec());
+        offset = start + len;
+    }
+
+    (records, offset)
+}
+
+/// An append-only write-ahead log. Each `append` is durable once it returns.
+pub struct Wal {
+    file: File,
+    /// Where the last complete record ends.
+    len: u64,
+    /// A failed append could not be rolled back, so the end of the file may
+    /// be garbage. Only `open` can recover from that.
+    broken: bool,
+}
+
+impl Wal {
+    /// Opens (or creates) the log and returns every intact record in it.
+    ///
+    /// A crash in the middle of `append` can leave a torn record at the end.
+    /// It is cut off here, so new records are never appended after garbage.
+    pub async fn open(
+        path: impl AsRef<Path>,
+    ) -> io::Result<(Self, Vec<Vec<u8>>)> {
+        let path = path.as_ref();
+        let log = match tokio::fs::read(path).await {
+            Ok(log) => log,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        let (records, valid_len) = decode(&log);
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        if valid_len < log.len() {
+            file.set_len(valid_len as u64).await?;
+            file.sync_all().await?;
+        }
+        // Make sure a newly created log file is durable too.
+        sync_dir(path).await?;
+
+        let wal = Self {
+            file,
+            len: valid_len as u64,
+            broken: false,
+        };
+        Ok((wal, records))
+    }
+
+    /// On error, nothing of the record is left in the log.
+    pub async fn append(&mut self, payload: &[u8]) -> io::Result<()> {
+        if self.broken {
+            return Err(io::Error::other(
+                "WAL must be reopened after an error",
+            ));
+        }
+        let record = encode(payload)?;
+        let written = async {
+            self.file.write_all(&record).await?;
+            // A failed background write only shows up here, not in
+            // `sync_data`.
+            self.file.flush().await?;
+            // `sync_data` skips metadata such as timestamps, but the file
+            // length is included, which is all we need for an append.
+            self.file.sync_data().await
+        };
+        match written.await {
+            Ok(()) => {
+                self.len += record.len() as u64;
+                Ok(())
+            }
+            Err(err) => {
+                // Part of the record may have been written. Cut it off, or
+                // the next record would be appended after it, where
+                // recovery never reaches.
+                if self.file.set_len(self.len).await.is_err() {
+                    self.broken = true;
+                }
+                Err(err)
+            }
+        }
+    }
+}
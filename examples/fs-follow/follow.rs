@@ -0,0 +1,147 @@
+use std::{
+    io::{self, SeekFrom},
+    os::unix::fs::MetadataExt,
+    path::PathBuf,
+    time::Duration,
+};
+
+use tokio::{
+    fs::{self, File},
+    io::{AsyncBufReadExt, AsyncSeekExt, BufReader},
+    sync::mpsc,
+    time::sleep,
+};
+
+use crate::channel_stream::ChannelStream;
+
+/// Where to start reading the file when following begins.
+#[derive(Debug, Clone, Copy)]
+pub enum StartAt {
+    Beginning,
+    /// Only lines appended after we started, like `tail -f`.
+    End,
+}
+
+/// An open file together with the identity and position we last saw.
+struct Followed {
+    reader: BufReader<File>,
+    inode: u64,
+    position: u64,
+}
+
+async fn open(path: &PathBuf, start: StartAt) -> io::Result<Followed> {
+    let file = File::open(path).await?;
+    let inode = file.metadata().await?.ino();
+    let mut reader = BufReader::new(file);
+    let position = match start {
+        StartAt::Beginning => 0,
+        StartAt::End => reader.seek(SeekFrom::End(0)).await?,
+    };
+    Ok(Followed {
+        reader,
+        inode,
+        position,
+    })
+}
+
+/// Follows `path` like `tail -F`: yields every complete line appended to it,
+/// and keeps going when the file is truncated or replaced by a new file.
+///
+/// The file is opened before this returns, so "the end" for `StartAt::End`
+/// is well defined and a missing file is reported right away.
+pub async fn follow(
+    path: impl Into<PathBuf>,
+    start: StartAt,
+    poll_interval: Duration,
+) -> io::Result<ChannelStream<io::Result<String>>> {
+    let path = path.into();
+    let current = open(&path, start).await?;
+    let (tx, rx) = mpsc::channel(64);
+    let task = tokio::spawn(async move {
+        if let Err(err) = follow_loop(path, current, poll_interval, &tx).await {
+            let _ = tx.send(Err(err)).await;
+        }
+    });
+    Ok(ChannelStream::with_task(rx, task))
+}
+
+/// Reads `current` up to EOF, sending every complete line. A trailing
+/// partial line stays in `line_buffer`. Returns `false` once the stream has
+/// been dropped.
+async fn read_lines(
+    current: &mut Followed,
+    line_buffer: &mut String,
+    tx: &mpsc::Sender<io::Result<String>>,
+) -> io::Result<bool> {
+    loop {
+        // The same `read_line` loop as in `io-buffered.rs`...
+        let n = current.reader.read_line(line_buffer).await?;
+        if n == 0 {
+            return Ok(true);
+        }
+        current.position += n as u64;
+        if line_buffer.ends_with('\n') {
+            let line = line_buffer.trim_end_matches(['\r', '\n']);
+            if tx.send(Ok(line.to_string())).await.is_err() {
+                return Ok(false);
+            }
+            line_buffer.clear();
+        }
+    }
+}
+
+async fn follow_loop(
+    path: PathBuf,
+    mut current: Followed,
+    poll_interval: Duration,
+    tx: &mpsc::Sender<io::Result<String>>,
+) -> io::Result<()> {
+    // A line is only emitted once its '\n' has been written. Until then the
+    // partial line stays in here, across polls.
+    let mut line_buffer = String::new();
+
+    loop {
+        if !read_lines(&mut current, &mut line_buffer, tx).await? {
+            return Ok(()); // the stream was dropped
+        }
+
+        // ...except that EOF is not the end: check what happened to the file
+        // and then poll again.
+        match fs::metadata(&path).await {
+            Ok(meta) if meta.ino() != current.inode => {
+                // Rotated: the path now names a new file. The writer may have
+                // appended to the old one since our last EOF, so drain it
+                // first. Only then is a line without '\n' really its last
+                // line: emit it as it is, then switch over.
+                if !read_lines(&mut current, &mut line_buffer, tx).await? {
+                    return Ok(());
+                }
+                if !line_buffer.is_empty() {
+                    let line = line_buffer.trim_end_matches('\r');
+                    if tx.send(Ok(line.to_string())).await.is_err() {
+                        return Ok(());
+                    }
+                    line_buffer.clear();
+                }
+                current = open(&path, StartAt::Beginning).await?;
+                continue;
+            }
+            Ok(meta) if meta.len() < current.position => {
+                // Truncated in place: start over from the beginning.
+                current.position =
+                    current.reader.seek(SeekFrom::Start(0)).await?;
+                line_buffer.clear();
+                continue;
+            }
+            // Nothing new, or the path is missing in the middle of a rotation.
+            Ok(_) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        if tx.is_closed() {
+            return Ok(());
+        }
+        sleep(poll_interval).await;
+    }
+}
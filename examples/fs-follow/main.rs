@@ -0,0 +1,133 @@
+#[path = "../common/channel_stream.rs"]
+mod channel_stream;
+mod follow;
+#[path = "../common/temp.rs"]
+mod temp;
+mod watch;
+
+use std::{path::Path, time::Duration};
+
+use colored::Colorize;
+use futures_core::Stream;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, time::timeout};
+
+use channel_stream::next;
+use follow::StartAt;
+use temp::TempDir;
+use watch::Event;
+
+const POLL: Duration = Duration::from_millis(20);
+
+/// Waits for the next item, failing instead of hanging if none arrives.
+async fn expect_next<S, T>(stream: &mut S) -> anyhow::Result<T>
+where
+    S: Stream<Item = std::io::Result<T>> + Unpin,
+{
+    let item = timeout(Duration::from_secs(2), next(stream)).await?;
+    Ok(item.ok_or_else(|| anyhow::anyhow!("stream ended"))??)
+}
+
+async fn append(path: &Path, text: &str) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new().append(true).open(path).await?;
+    file.write_all(text.as_bytes()).await?;
+    Ok(())
+}
+
+async fn follow_demo(dir: &Path) -> anyhow::Result<()> {
+    let log = dir.join("app.log");
+    tokio::fs::write(&log, "old line, before we started\n").await?;
+
+    let mut lines = follow::follow(&log, StartAt::End, POLL).await?;
+
+    // --- Appended lines ---
+    append(&log, "first\nsecond\n").await?;
+    assert_eq!(expect_next(&mut lines).await?, "first");
+    assert_eq!(expect_next(&mut lines).await?, "second");
+
+    // --- A line written in two parts is emitted once, when complete ---
+    append(&log, "par").await?;
+    tokio::time::sleep(POLL * 3).await;
+    append(&log, "tial\n").await?;
+    assert_eq!(expect_next(&mut lines).await?, "partial");
+    println!("{}", "appended lines followed".green());
+
+    // --- Truncation: the file is emptied in place ---
+    tokio::time::sleep(POLL * 3).await;
+    OpenOptions::new()
+        .write(true)
+        .open(&log)
+        .await?
+        .set_len(0)
+        .await?;
+    tokio::time::sleep(POLL * 3).await;
+    append(&log, "after truncate\n").await?;
+    assert_eq!(expect_next(&mut lines).await?, "after truncate");
+    println!("{}", "truncation detected".green());
+
+    // --- Rotation: the file is renamed and a new one takes its place ---
+    append(&log, "last line of old file\n").await?;
+    tokio::fs::rename(&log, dir.join("app.log.1")).await?;
+    tokio::fs::write(&log, "first line of new file\n").await?;
+    assert_eq!(expect_next(&mut lines).await?, "last line of old file");
+    assert_eq!(expect_next(&mut lines).await?, "first line of new file");
+    append(&log, "more\n").await?;
+    assert_eq!(expect_next(&mut lines).await?, "more");
+
+    // --- Rotation right after an unterminated line: it is not lost ---
+    append(&log, "no newline at the end").await?;
+    tokio::time::sleep(POLL * 3).await;
+    tokio::fs::rename(&log, dir.join("app.log.2")).await?;
+    tokio::fs::write(&log, "next file\n").await?;
+    assert_eq!(expect_next(&mut lines).await?, "no newline at the end");
+    assert_eq!(expect_next(&mut lines).await?, "next file");
+
+    // --- A writer that still has the old file open keeps appending to it ---
+    let mut old = OpenOptions::new().append(true).open(&log).await?;
+    tokio::fs::rename(&log, dir.join("app.log.3")).await?;
+    old.write_all(b"written after the rename\n").await?;
+    old.flush().await?;
+    tokio::fs::write(&log, "after the late write\n").await?;
+    assert_eq!(expect_next(&mut lines).await?, "written after the rename");
+    assert_eq!(expect_next(&mut lines).await?, "after the late write");
+    println!("{}", "rotation detected".green());
+
+    Ok(())
+}
+
+async fn watch_demo(dir: &Path) -> anyhow::Result<()> {
+    let watched = dir.join("watched");
+    tokio::fs::create_dir(&watched).await?;
+    tokio::fs::write(watched.join("existing.txt"), "already here").await?;
+
+    let mut events = watch::watch(&watched, POLL).await?;
+
+    let file = watched.join("new.txt");
+    tokio::fs::write(&file, "hello").await?;
+    assert_eq!(
+        expect_next(&mut events).await?,
+        Event::Created(file.clone())
+    );
+
+    append(&file, " world").await?;
+    assert_eq!(
+        expect_next(&mut events).await?,
+        Event::Modified(file.clone())
+    );
+
+    tokio::fs::remove_file(&file).await?;
+    assert_eq!(
+        expect_next(&mut events).await?,
+        Event::Deleted(file.clone())
+    );
+    println!("{}", "create/modify/delete events observed".green());
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // Removed when dropped, even if one of the steps below fails
+    let dir = TempDir::new("follow").await?;
+    follow_demo(dir.path()).await?;
+    watch_demo(dir.path()).await
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::HashMap,
+    io,
+    os::unix::fs::MetadataExt,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use tokio::{fs, sync::mpsc, time::interval};
+
+use crate::channel_stream::ChannelStream;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Created(PathBuf),
+    Modified(PathBuf),
+    Deleted(PathBuf),
+}
+
+/// What we remember about a file between two scans. If any of it changes,
+/// the file was modified (or replaced).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fingerprint {
+    inode: u64,
+    len: u64,
+    modified: SystemTime,
+}
+
+async fn scan(dir: &PathBuf) -> io::Result<HashMap<PathBuf, Fingerprint>> {
+    let mut snapshot = HashMap::new();
+    let mut entries = fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        // A file may disappear between `read_dir` and `metadata`; it will
+        // simply be missing from this snapshot.
+        let Ok(meta) = entry.metadata().await else {
+            continue;
+        };
+        if meta.is_file() {
+            let fingerprint = Fingerprint {
+                inode: meta.ino(),
+                len: meta.len(),
+                modified: meta.modified()?,
+            };
+            snapshot.insert(entry.path(), fingerprint);
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Compares two snapshots. Events are sorted by path so the order does not
+/// depend on `HashMap` iteration order.
+fn diff(
+    before: &HashMap<PathBuf, Fingerprint>,
+    after: &HashMap<PathBuf, Fingerprint>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+    for (path, fingerprint) in after {
+        match before.get(path) {
+            None => events.push(Event::Created(path.clone())),
+            Some(old) if old != fingerprint => {
+                events.push(Event::Modified(path.clone()))
+            }
+            Some(_) => {}
+        }
+    }
+    for path in before.keys() {
+        if !after.contains_key(path) {
+            events.push(Event::Deleted(path.clone()));
+        }
+    }
+    events.sort_by(|a, b| event_path(a).cmp(event_path(b)));
+    events
+}
+
+fn event_path(event: &Event) -> &PathBuf {
+    match event {
+        Event::Created(path) | Event::Modified(path) | Event::Deleted(path) => {
+            path
+        }
+    }
+}
+
+/// Watches the files directly inside `dir` by rescanning it every
+/// `poll_interval`. Files present when this returns produce no events.
+pub async fn watch(
+    dir: impl Into<PathBuf>,
+    poll_interval: Duration,
+) -> io::Result<ChannelStream<io::Result<Event>>> {
+    let dir = dir.into();
+    let mut known = scan(&dir).await?;
+    let (tx, rx) = mpsc::channel(64);
+    let task = tokio::spawn(async move {
+        let mut ticker = interval(poll_interval);
+        loop {
+            ticker.tick().await;
+            let current = match scan(&dir).await {
+                Ok(snapshot) => snapshot,
+                Err(err) => {
+                    let _ = tx.send(Err(err)).await;
+                    return;
+                }
+            };
+            for event in diff(&known, &current) {
+                if tx.send(Ok(event)).await.is_err() {
+                    return;
+                }
+            }
+            known = current;
+        }
+    });
+    Ok(ChannelStream::with_task(rx, task))
+}
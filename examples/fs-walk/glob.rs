@@ -0,0 +1,111 @@
+/// A small glob matcher supporting the usual wildcards:
+///
+/// * `?` matches exactly one character other than `/`.
+/// * `*` matches any run of characters other than `/`.
+/// * `**` matches any run of characters, including `/`.
+///
+/// A pattern without a `/` is matched against the file name only, so `*.rs`
+/// matches Rust files at any depth. Otherwise it is matched against the path
+/// relative to the walk root, e.g. `src/**/*.rs`.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: Vec<char>,
+    name_only: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.chars().collect(),
+            name_only: !pattern.contains('/'),
+        }
+    }
+
+    /// `relative` uses `/` as separator regardless of the platform.
+    pub fn matches(&self, relative: &str) -> bool {
+        let candidate = if self.name_only {
+            relative.rsplit('/').next().unwrap_or(relative)
+        } else {
+            relative
+        };
+        let candidate: Vec<char> = candidate.chars().collect();
+        matches(&self.pattern, &candidate)
+    }
+}
+
+/// The classic two-pointer wildcard matcher: on a mismatch, only the most
+/// recent `*` takes one more character, so there is no exponential
+/// backtracking. A `*` cannot take a `/`; then the most recent `**` takes one
+/// more character instead, or one more directory for `**/`.
+fn matches(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the last `*`: the pattern after it, and the text
+    // position the rest was tried at.
+    let mut star: Option<(usize, usize)> = None;
+    // The same for the last `**`, and whether it is `**/`.
+    let mut globstar: Option<(usize, usize, bool)> = None;
+
+    loop {
+        if let Some(&c) = pattern.get(p) {
+            let next = text.get(t).copied();
+            match c {
+                '*' if pattern.get(p + 1) == Some(&'*') => {
+                    // `**/` may also match zero directories: `a/**/b`
+                    // matches `a/b`.
+                    let dirs = pattern.get(p + 2) == Some(&'/');
+                    p += if dirs { 3 } else { 2 };
+                    globstar = Some((p, t, dirs));
+                    star = None;
+                    continue;
+                }
+                '*' => {
+                    p += 1;
+                    star = Some((p, t));
+                    continue;
+                }
+                '?' if next.is_some_and(|n| n != '/') => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                c if c != '?' && next == Some(c) => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                _ => {}
+            }
+        } else if t == text.len() {
+            return true;
+        }
+
+        // Mismatch: backtrack to the last `*` if it can take one more
+        // character.
+        if let Some((sp, st)) = star
+            && text.get(st).is_some_and(|&c| c != '/')
+        {
+            star = Some((sp, st + 1));
+            (p, t) = (sp, st + 1);
+            continue;
+        }
+        // Or else to the last `**`. Matching from there meets any `*`
+        // after it again.
+        let Some((gp, gt, dirs)) = globstar else {
+            return false;
+        };
+        let resume = if dirs {
+            text[gt..]
+                .iter()
+                .position(|&c| c == '/')
+                .map(|i| gt + i + 1)
+        } else {
+            (gt < text.len()).then_some(gt + 1)
+        };
+        let Some(gt) = resume else {
+            return false;
+        };
+        globstar = Some((gp, gt, dirs));
+        star = None;
+        (p, t) = (gp, gt);
+    }
+}
@@ -0,0 +1,216 @@
+mod glob;
+#[path = "../common/temp.rs"]
+mod temp;
+mod walker;
+
+use std::{
+    collections::HashMap,
+    hash::Hasher,
+    io::Read,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use colored::Colorize;
+use tokio::task::{self, JoinSet};
+
+use glob::Glob;
+use temp::TempDir;
+use walker::{Kind, WalkOptions};
+
+/// Hashes a file's contents. Runs on the blocking thread pool, so plain
+/// `std::fs` is fine here.
+fn hash_file(path: &Path) -> std::io::Result<u64> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = std::hash::DefaultHasher::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.write(&buf[..n]);
+    }
+}
+
+/// Whether two files have the same contents, compared byte by byte. Equal
+/// hashes only make that likely.
+fn same_contents(a: &Path, b: &Path) -> std::io::Result<bool> {
+    const CHUNK: u64 = 64 * 1024;
+    let (mut a, mut b) = (std::fs::File::open(a)?, std::fs::File::open(b)?);
+    let (mut chunk_a, mut chunk_b) = (Vec::new(), Vec::new());
+    loop {
+        chunk_a.clear();
+        chunk_b.clear();
+        (&mut a).take(CHUNK).read_to_end(&mut chunk_a)?;
+        (&mut b).take(CHUNK).read_to_end(&mut chunk_b)?;
+        if chunk_a != chunk_b {
+            return Ok(false);
+        }
+        if chunk_a.is_empty() {
+            return Ok(true);
+        }
+    }
+}
+
+/// Splits files with the same length and hash into groups whose contents
+/// really are the same.
+fn confirm(files: Vec<(String, PathBuf)>) -> std::io::Result<Vec<Vec<String>>> {
+    let mut groups: Vec<(PathBuf, Vec<String>)> = Vec::new();
+    'files: for (relative, path) in files {
+        for (first, group) in &mut groups {
+            if same_contents(first, &path)? {
+                group.push(relative);
+                continue 'files;
+            }
+        }
+        groups.push((path, vec![relative]));
+    }
+    Ok(groups.into_iter().map(|(_, group)| group).collect())
+}
+
+fn glob_cases() {
+    let cases = [
+        ("*.rs", "src/main.rs", true),
+        ("src/*.rs", "src/bin/tool.rs", false),
+        ("src/**/*.rs", "src/main.rs", true),
+        ("src/**/*.rs", "src/bin/tool.rs", true),
+        ("src/**", "src/bin/tool.rs", true),
+        ("**/b/*.txt", "a/b/c/b/x.txt", true),
+        ("**/b/*.txt", "a/b/c/x.txt", false),
+        ("a/**/b", "a/xb", false),
+        ("a?c", "abc", true),
+        ("a/?", "a//", false),
+    ];
+    for (pattern, path, expected) in cases {
+        assert_eq!(Glob::new(pattern).matches(path), expected, "{}", pattern);
+    }
+
+    // A backtracking matcher takes exponential time on this one.
+    let start = Instant::now();
+    let text = "a".repeat(100);
+    assert!(!Glob::new("*a*a*a*a*a*a*a*b").matches(&text));
+    assert!(!Glob::new("**a**a**a**a**a**b").matches(&text));
+    assert!(start.elapsed().as_millis() < 100, "{:?}", start.elapsed());
+}
+
+async fn build_tree(root: &Path) -> anyhow::Result<()> {
+    let files = [
+        ("a.txt", "same content"),
+        ("b.txt", "unique"),
+        ("docs/guide.md", "# Guide"),
+        ("docs/copy-of-a.txt", "same content"),
+        ("docs/deep/nested/again-a.txt", "same content"),
+        ("src/main.rs", "fn main() {}"),
+        ("src/lib.rs", "pub fn lib() {}"),
+    ];
+    for (path, contents) in files {
+        let path = root.join(path);
+        tokio::fs::create_dir_all(path.parent().unwrap()).await?;
+        tokio::fs::write(path, contents).await?;
+    }
+    // A symlink to a file, one back up to the root (a loop) and a dangling one.
+    tokio::fs::symlink(root.join("b.txt"), root.join("docs/link-to-b.txt"))
+        .await?;
+    tokio::fs::symlink(root, root.join("docs/deep/loop")).await?;
+    tokio::fs::symlink(root.join("missing"), root.join("dangling")).await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    glob_cases();
+
+    let dir = TempDir::new("fs-walk").await?;
+    let root = dir.path();
+    build_tree(root).await?;
+
+    // --- Not following symlinks: links are reported as such ---
+    let entries = walker::collect(root, WalkOptions::default()).await?;
+    println!("Walk without following symlinks:");
+    for entry in &entries {
+        let indent = "  ".repeat(entry.depth);
+        println!("{}{:?} {}", indent, entry.kind, entry.relative);
+    }
+    let links = entries.iter().filter(|e| e.kind == Kind::Symlink).count();
+    assert_eq!(links, 3);
+    assert_eq!(entries.iter().filter(|e| e.kind == Kind::File).count(), 7);
+
+    // --- Following symlinks: the loop back to the root is detected ---
+    let options = WalkOptions {
+        follow_symlinks: true,
+        ..Default::default()
+    };
+    let entries = walker::collect(root, options).await?;
+    let files = entries.iter().filter(|e| e.kind == Kind::File).count();
+    assert_eq!(files, 8); // the 7 files plus the link to b.txt
+    assert!(entries.iter().all(|e| e.kind != Kind::Symlink));
+    println!("Following symlinks: {} files, loop detected", files);
+
+    // --- Glob filtering ---
+    let options = WalkOptions {
+        glob: Some(Glob::new("src/**/*.rs")),
+        ..Default::default()
+    };
+    let rust_files: Vec<_> = walker::collect(root, options)
+        .await?
+        .into_iter()
+        .map(|e| e.relative)
+        .collect();
+    assert_eq!(rust_files, ["src/lib.rs", "src/main.rs"]);
+    println!("Glob src/**/*.rs: {:?}", rust_files);
+
+    // --- Hash every *.txt file in parallel and report duplicates ---
+    let options = WalkOptions {
+        glob: Some(Glob::new("*.txt")),
+        max_concurrency: 4,
+        ..Default::default()
+    };
+    let mut entries = walker::walk(root, options);
+    let mut hashing = JoinSet::new();
+    while let Some(entry) = entries.recv().await {
+        let entry = entry?;
+        if entry.kind != Kind::File {
+            continue;
+        }
+        // Hashing starts while the walk is still running.
+        hashing.spawn_blocking(move || {
+            let hash = hash_file(&entry.path);
+            (entry.relative, entry.path, entry.len, hash)
+        });
+    }
+
+    let mut by_content: HashMap<(u64, u64), Vec<(String, PathBuf)>> =
+        HashMap::new();
+    while let Some(result) = hashing.join_next().await {
+        let (relative, path, len, hash) = result?;
+        by_content
+            .entry((len, hash?))
+            .or_default()
+            .push((relative, path));
+    }
+
+    // Same length and hash are only candidates: compare their bytes.
+    let mut duplicates = Vec::new();
+    for files in by_content.into_values().filter(|files| files.len() > 1) {
+        let groups = task::spawn_blocking(move || confirm(files)).await??;
+        duplicates.extend(groups.into_iter().filter(|g| g.len() > 1));
+    }
+    for group in &mut duplicates {
+        group.sort();
+        println!("{} {}", "Duplicates:".yellow(), group.join(", "));
+    }
+    assert_eq!(
+        duplicates,
+        [[
+            "a.txt",
+            "docs/copy-of-a.txt",
+            "docs/deep/nested/again-a.txt"
+        ]]
+    );
+
+    // Clean up
+    dir.close().await?;
+
+    Ok(())
+}
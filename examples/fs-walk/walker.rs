@@ -0,0 +1,239 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use tokio::{fs, sync::mpsc, task::JoinSet};
+
+use crate::glob::Glob;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Dir,
+    /// Only reported when symlinks are not followed.
+    Symlink,
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// Path relative to the walk root, with `/` as separator.
+    pub relative: String,
+    pub kind: Kind,
+    pub len: u64,
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    /// Descend into symlinked directories and report symlinked files as files.
+    pub follow_symlinks: bool,
+    /// Maximum number of directories being read at the same time.
+    pub max_concurrency: usize,
+    /// Only entries matching the glob are reported (directories are still
+    /// descended into).
+    pub glob: Option<Glob>,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            follow_symlinks: false,
+            max_concurrency: 8,
+            glob: None,
+        }
+    }
+}
+
+/// A directory still to be read.
+struct Pending {
+    path: PathBuf,
+    relative: String,
+    depth: usize,
+}
+
+/// Walks `root` recursively and streams every entry over the returned channel.
+///
+/// Directories are read by up to `max_concurrency` tasks at once, and the
+/// channel's capacity provides backpressure when the consumer is slow.
+pub fn walk(
+    root: impl Into<PathBuf>,
+    options: WalkOptions,
+) -> mpsc::Receiver<io::Result<Entry>> {
+    let (tx, rx) = mpsc::channel(64);
+    let root = root.into();
+    tokio::spawn(async move {
+        if let Err(err) = coordinate(root, options, tx.clone()).await {
+            let _ = tx.send(Err(err)).await;
+        }
+    });
+    rx
+}
+
+async fn coordinate(
+    root: PathBuf,
+    options: WalkOptions,
+    tx: mpsc::Sender<io::Result<Entry>>,
+) -> io::Result<()> {
+    // Directories are identified by (device, inode). With symlinks followed,
+    // the same directory can be reached through several paths, and a link to
+    // one of its own ancestors would otherwise make us loop forever.
+    let mut visited = HashSet::new();
+    let root_meta = fs::metadata(&root).await?;
+    visited.insert((root_meta.dev(), root_meta.ino()));
+
+    let mut queue = VecDeque::from([Pending {
+        path: root,
+        relative: String::new(),
+        depth: 0,
+    }]);
+    let mut readers = JoinSet::new();
+
+    while !queue.is_empty() || !readers.is_empty() {
+        // The consumer went away: stop walking. Dropping `readers` aborts
+        // the directories still being read.
+        if tx.is_closed() {
+            return Ok(());
+        }
+        while readers.len() < options.max_concurrency.max(1) {
+            let Some(dir) = queue.pop_front() else { break };
+            readers.spawn(read_dir(dir, options.clone(), tx.clone()));
+        }
+
+        let Some(joined) = readers.join_next().await else {
+            break;
+        };
+        let subdirs = match joined? {
+            Ok(subdirs) => subdirs,
+            // An unreadable directory is reported but does not stop the walk.
+            Err(err) => {
+                if tx.send(Err(err)).await.is_err() {
+                    return Ok(());
+                }
+                continue;
+            }
+        };
+        for (dir, id) in subdirs {
+            if visited.insert(id) {
+                queue.push_back(dir);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads a single directory, sends its entries and returns the
+/// subdirectories to descend into next.
+///
+/// An entry that cannot be inspected, e.g. because it was deleted while we
+/// walk, is reported and skipped; the rest of the directory is still read.
+async fn read_dir(
+    dir: Pending,
+    options: WalkOptions,
+    tx: mpsc::Sender<io::Result<Entry>>,
+) -> io::Result<Vec<(Pending, (u64, u64))>> {
+    let mut subdirs = Vec::new();
+    let mut entries = fs::read_dir(&dir.path).await?;
+
+    loop {
+        if tx.is_closed() {
+            break; // the consumer went away, stop walking
+        }
+        let dir_entry = match entries.next_entry().await {
+            Ok(Some(dir_entry)) => dir_entry,
+            Ok(None) => break,
+            // Keep the subdirectories found so far.
+            Err(err) => {
+                let _ = tx.send(Err(err)).await;
+                break;
+            }
+        };
+        let path = dir_entry.path();
+        let name = dir_entry.file_name().to_string_lossy().into_owned();
+        let relative = if dir.relative.is_empty() {
+            name
+        } else {
+            format!("{}/{}", dir.relative, name)
+        };
+
+        // `DirEntry::file_type` does not follow symlinks.
+        let inspected = async {
+            let mut file_type = dir_entry.file_type().await?;
+            let mut metadata = dir_entry.metadata().await?;
+            if file_type.is_symlink() && options.follow_symlinks {
+                match fs::metadata(&path).await {
+                    Ok(target) => {
+                        file_type = target.file_type();
+                        metadata = target;
+                    }
+                    // Dangling symlink: nothing to follow.
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                        return Ok(None);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(Some((file_type, metadata)))
+        };
+        let (file_type, metadata) = match inspected.await {
+            Ok(Some(inspected)) => inspected,
+            Ok(None) => continue,
+            Err(err) => {
+                if tx.send(Err(err)).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        let kind = if file_type.is_dir() {
+            Kind::Dir
+        } else if file_type.is_symlink() {
+            Kind::Symlink
+        } else {
+            Kind::File
+        };
+
+        if kind == Kind::Dir {
+            let pending = Pending {
+                path: path.clone(),
+                relative: relative.clone(),
+                depth: dir.depth + 1,
+            };
+            subdirs.push((pending, (metadata.dev(), metadata.ino())));
+        }
+
+        if options.glob.as_ref().is_some_and(|g| !g.matches(&relative)) {
+            continue;
+        }
+        let entry = Entry {
+            path,
+            relative,
+            kind,
+            len: metadata.len(),
+            depth: dir.depth + 1,
+        };
+        if tx.send(Ok(entry)).await.is_err() {
+            break; // the consumer went away, stop walking
+        }
+    }
+
+    Ok(subdirs)
+}
+
+/// Convenience for tests and small trees: collects the whole walk.
+pub async fn collect(
+    root: &Path,
+    options: WalkOptions,
+) -> io::Result<Vec<Entry>> {
+    let mut rx = walk(root, options);
+    let mut entries = Vec::new();
+    while let Some(entry) = rx.recv().await {
+        entries.push(entry?);
+    }
+    entries.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok(entries)
+}
@@ -0,0 +1,242 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+pub use crate::crc32::Crc32;
+
+/// An incremental checksum: feed it data in any number of pieces and get the
+/// same result as hashing everything at once.
+pub trait Checksum {
+    fn update(&mut self, data: &[u8]);
+    /// The checksum of everything fed so far. Does not reset the state.
+    fn digest(&self) -> Vec<u8>;
+}
+
+/// CRC-32 (IEEE), the checksum of zip, gzip and PNG.
+impl Checksum for Crc32 {
+    fn update(&mut self, data: &[u8]) {
+        Crc32::update(self, data);
+    }
+
+    fn digest(&self) -> Vec<u8> {
+        self.value().to_be_bytes().to_vec()
+    }
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1,
+    0x923f82a4, 0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+    0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147,
+    0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+    0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+    0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 as specified in FIPS 180-4. Fine for checking data integrity in
+/// an example; use an audited crate such as `sha2` for anything real.
+#[derive(Debug, Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    /// Bytes not yet processed, always fewer than one 64-byte block.
+    pending: Vec<u8>,
+    len: u64,
+}
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Self {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f,
+                0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+            ],
+            pending: Vec::with_capacity(64),
+            len: 0,
+        }
+    }
+}
+
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    for (i, word) in block.chunks_exact(4).enumerate() {
+        w[i] = u32::from_be_bytes(word.try_into().unwrap());
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7)
+            ^ w[i - 15].rotate_right(18)
+            ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17)
+            ^ w[i - 2].rotate_right(19)
+            ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+impl Checksum for Sha256 {
+    fn update(&mut self, data: &[u8]) {
+        self.len += data.len() as u64;
+        self.pending.extend_from_slice(data);
+        let full = self.pending.len() / 64 * 64;
+        for block in self.pending[..full].chunks_exact(64) {
+            compress(&mut self.state, block);
+        }
+        self.pending.drain(..full);
+    }
+
+    fn digest(&self) -> Vec<u8> {
+        // Padding: a 1 bit, zeros, then the message length in bits, so that
+        // the total is a multiple of 64 bytes.
+        let mut state = self.state;
+        let mut tail = self.pending.clone();
+        tail.push(0x80);
+        while tail.len() % 64 != 56 {
+            tail.push(0);
+        }
+        tail.extend_from_slice(&(self.len * 8).to_be_bytes());
+        for block in tail.chunks_exact(64) {
+            compress(&mut state, block);
+        }
+        state.iter().flat_map(|word| word.to_be_bytes()).collect()
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Passes reads through unchanged while feeding every byte to a checksum.
+/// Once the inner reader reports EOF, the digest is available.
+pub struct HashingReader<R, C> {
+    inner: R,
+    checksum: C,
+    digest: Option<Vec<u8>>,
+}
+
+impl<R, C: Checksum> HashingReader<R, C> {
+    pub fn new(inner: R, checksum: C) -> Self {
+        Self {
+            inner,
+            checksum,
+            digest: None,
+        }
+    }
+
+    /// `None` until everything has been read.
+    pub fn digest(&self) -> Option<&[u8]> {
+        self.digest.as_deref()
+    }
+}
+
+impl<R, C> AsyncRead for HashingReader<R, C>
+where
+    R: AsyncRead + Unpin,
+    C: Checksum + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            let new = &buf.filled()[before..];
+            // Reading nothing into a buffer with room left means EOF.
+            if new.is_empty() && buf.remaining() > 0 {
+                this.digest.get_or_insert_with(|| this.checksum.digest());
+            } else {
+                this.checksum.update(new);
+            }
+        }
+        result
+    }
+}
+
+/// Passes writes through unchanged while feeding every byte the inner writer
+/// accepted to a checksum.
+pub struct HashingWriter<W, C> {
+    inner: W,
+    checksum: C,
+}
+
+impl<W, C: Checksum> HashingWriter<W, C> {
+    pub fn new(inner: W, checksum: C) -> Self {
+        Self { inner, checksum }
+    }
+
+    /// The checksum of everything written so far.
+    pub fn digest(&self) -> Vec<u8> {
+        self.checksum.digest()
+    }
+}
+
+impl<W, C> AsyncWrite for HashingWriter<W, C>
+where
+    W: AsyncWrite + Unpin,
+    C: Checksum + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        // Only the first `n` bytes were written; the caller retries the rest.
+        if let Poll::Ready(Ok(n)) = result {
+            this.checksum.update(&buf[..n]);
+        }
+        result
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,244 @@
+mod checksum;
+#[path = "../common/crc32.rs"]
+mod crc32;
+mod rle;
+#[path = "../common/temp.rs"]
+mod temp;
+
+use colored::Colorize;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+
+use checksum::{Checksum, Crc32, HashingReader, HashingWriter, Sha256, hex};
+use rle::{RleReader, RleWriter, compress, decompress};
+use temp::{TempDir, TempFile};
+
+fn sha256(data: &[u8]) -> Vec<u8> {
+    let mut sha = Sha256::new();
+    sha.update(data);
+    sha.digest()
+}
+
+fn crc32(data: &[u8]) -> Vec<u8> {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.digest()
+}
+
+async fn known_answers() -> anyhow::Result<()> {
+    let cases: [(&[u8], &str, &str); 3] = [
+        (
+            b"",
+            "00000000",
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        ),
+        (
+            b"abc",
+            "352441c2",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        (
+            b"123456789",
+            "cbf43926",
+            "15e2b0d3c33891ebb0f1ef609ec419420c20e320ce94c65fbc8c3312448eb225",
+        ),
+    ];
+    for (input, crc, sha) in cases {
+        let mut reader = HashingReader::new(input, Sha256::new());
+        assert!(reader.digest().is_none());
+        io::copy(&mut reader, &mut io::sink()).await?;
+        assert_eq!(hex(reader.digest().unwrap()), sha);
+        assert_eq!(hex(&crc32(input)), crc);
+    }
+    // 1,000,000 times 'a' crosses many block boundaries.
+    let mut reader = HashingReader::new(&[b'a'; 1_000_000][..], Sha256::new());
+    io::copy(&mut reader, &mut io::sink()).await?;
+    assert_eq!(
+        hex(reader.digest().unwrap()),
+        "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+    );
+    println!("{}", "CRC-32 and SHA-256 match the test vectors".green());
+    Ok(())
+}
+
+/// Compresses a file into another with `io::copy`, hashing on both sides,
+/// then decompresses it again and checks that nothing changed.
+async fn pipeline_demo() -> anyhow::Result<()> {
+    // Something that compresses well, like a simple bitmap: long runs of the
+    // same byte with a bit of noise in between.
+    let mut data = Vec::new();
+    for row in 0..200u32 {
+        data.extend(std::iter::repeat_n(b'.', 300 + (row as usize % 17)));
+        data.extend(row.to_be_bytes());
+        data.extend(std::iter::repeat_n(b'#', 40));
+    }
+    let input = TempFile::with_contents("rle-input", &data).await?;
+    let dir = TempDir::new("rle").await?;
+    let compressed = dir.join("input.rle");
+
+    // file -> hash plaintext -> compress -> hash compressed -> file
+    let mut reader =
+        HashingReader::new(tokio::fs::File::open(&input).await?, Sha256::new());
+    let mut writer = RleWriter::new(HashingWriter::new(
+        tokio::fs::File::create(&compressed).await?,
+        Crc32::new(),
+    ));
+    let copied = io::copy(&mut reader, &mut writer).await?;
+    writer.shutdown().await?;
+    let original_sha = reader.digest().unwrap().to_vec();
+    let compressed_crc = writer.into_inner().digest();
+
+    let compressed_len = tokio::fs::metadata(&compressed).await?.len();
+    println!(
+        "{} {} bytes -> {} bytes, sha256 {}, crc32 {}",
+        "compressed".green(),
+        copied,
+        compressed_len,
+        &hex(&original_sha)[..16],
+        hex(&compressed_crc)
+    );
+    assert!(compressed_len < copied / 10);
+    assert_eq!(crc32(&tokio::fs::read(&compressed).await?), compressed_crc);
+
+    // file -> decompress -> hash plaintext -> nowhere
+    let mut reader = HashingReader::new(
+        RleReader::new(tokio::fs::File::open(&compressed).await?),
+        Sha256::new(),
+    );
+    let restored = io::copy(&mut reader, &mut io::sink()).await?;
+    assert_eq!(restored, copied);
+    assert_eq!(reader.digest().unwrap(), original_sha);
+    println!("{}", "decompressed file has the same SHA-256".green());
+    Ok(())
+}
+
+/// Random test input: plain noise, runs of random length, or a mix.
+fn random_input(rng: &mut StdRng) -> Vec<u8> {
+    // Lengths near the packet limits are the interesting ones.
+    let len = match rng.random_range(0..4) {
+        0 => rng.random_range(0..4),
+        1 => rng.random_range(126..132),
+        2 => rng.random_range(255..262),
+        _ => rng.random_range(0..5000),
+    };
+    let mut data = Vec::with_capacity(len);
+    match rng.random_range(0..3) {
+        0 => data.extend((0..len).map(|_| rng.random::<u8>())),
+        1 => {
+            while data.len() < len {
+                let run = rng.random_range(1..300).min(len - data.len());
+                // A small alphabet also creates runs across run boundaries.
+                let byte = rng.random_range(0..3u8);
+                data.extend(std::iter::repeat_n(byte, run));
+            }
+        }
+        _ => {
+            while data.len() < len {
+                if rng.random_bool(0.5) {
+                    data.push(rng.random());
+                } else {
+                    let run = rng.random_range(1..20).min(len - data.len());
+                    data.extend(std::iter::repeat_n(rng.random::<u8>(), run));
+                }
+            }
+        }
+    }
+    data
+}
+
+/// Splits `len` bytes into random chunk sizes.
+fn random_chunks(rng: &mut StdRng, len: usize) -> Vec<usize> {
+    let mut chunks = Vec::new();
+    let mut left = len;
+    while left > 0 {
+        let n = rng.random_range(1..=left.min(300));
+        chunks.push(n);
+        left -= n;
+    }
+    chunks
+}
+
+async fn round_trip_properties() -> anyhow::Result<()> {
+    let seed: u64 = rand::random();
+    println!("property test seed: {}", seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let cases = 300;
+
+    for case in 0..cases {
+        let data = random_input(&mut rng);
+        let ctx = format!("seed {} case {} len {}", seed, case, data.len());
+
+        // In memory: decoding undoes encoding, and the output grows by at
+        // most one header byte per 128 input bytes.
+        let packed = compress(&data);
+        assert_eq!(decompress(&packed)?, data, "{}", ctx);
+        assert!(
+            packed.len() <= data.len() + data.len().div_ceil(128),
+            "{}",
+            ctx
+        );
+
+        // A truncated stream either fails or decodes to a prefix.
+        let cut = rng.random_range(0..=packed.len());
+        if let Ok(prefix) = decompress(&packed[..cut]) {
+            assert!(data.starts_with(&prefix), "{}", ctx);
+        }
+
+        // A checksum fed in pieces equals the checksum of the whole.
+        let mut sha = Sha256::new();
+        let mut offset = 0;
+        for n in random_chunks(&mut rng, data.len()) {
+            sha.update(&data[offset..offset + n]);
+            offset += n;
+        }
+        assert_eq!(sha.digest(), sha256(&data), "{}", ctx);
+
+        // Streaming through a small pipe, so both adapters see short reads
+        // and writes, and writes in random chunks with random flushes.
+        let (client, server) = io::duplex(rng.random_range(1..64));
+        let chunks = random_chunks(&mut rng, data.len());
+        let flushes: Vec<bool> =
+            chunks.iter().map(|_| rng.random_bool(0.1)).collect();
+
+        let write = async {
+            let mut writer =
+                RleWriter::new(HashingWriter::new(client, Crc32::new()));
+            let mut offset = 0;
+            for (n, flush) in chunks.iter().zip(&flushes) {
+                writer.write_all(&data[offset..offset + n]).await?;
+                if *flush {
+                    writer.flush().await?;
+                }
+                offset += n;
+            }
+            writer.shutdown().await?;
+            io::Result::Ok(writer.into_inner().digest())
+        };
+        let read = async {
+            let mut compressed = HashingReader::new(server, Crc32::new());
+            let mut restored = Vec::new();
+            let mut reader = HashingReader::new(
+                RleReader::new(&mut compressed),
+                Sha256::new(),
+            );
+            reader.read_to_end(&mut restored).await?;
+            let sha = reader.digest().unwrap().to_vec();
+            let crc = compressed.digest().unwrap().to_vec();
+            io::Result::Ok((restored, sha, crc))
+        };
+        let (sent_crc, received) = tokio::join!(write, read);
+        let (restored, sha, received_crc) = received?;
+        assert_eq!(restored, data, "{}", ctx);
+        assert_eq!(sha, sha256(&data), "{}", ctx);
+        assert_eq!(sent_crc?, received_crc, "{}", ctx);
+    }
+    println!("{} {} cases", "round trips ok:".green(), cases);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    known_answers().await?;
+    pipeline_demo().await?;
+    round_trip_properties().await
+}
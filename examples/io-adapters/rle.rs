@@ -0,0 +1,285 @@
+//! Run-length encoding in the PackBits format (used by TIFF and classic Mac
+//! OS). The stream is a sequence of packets, each starting with a header:
+//!
+//! * `0..=127`: `header + 1` literal bytes follow.
+//! * `128..=255`: the next byte is repeated `header - 126` times (2 to 129).
+//!
+//! Random data grows by at most one byte in 128; long runs shrink to 2 bytes
+//! per 129.
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const MAX_LITERAL: usize = 128;
+const MAX_RUN: usize = 129;
+
+/// Encoder state. Bytes are pushed one at a time; a packet is only emitted
+/// once it is clear that it cannot grow any further.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    literal: Vec<u8>,
+    run_byte: u8,
+    run_len: usize,
+}
+
+impl Encoder {
+    pub fn push(&mut self, byte: u8, out: &mut Vec<u8>) {
+        if self.run_len > 0 && byte == self.run_byte && self.run_len < MAX_RUN {
+            self.run_len += 1;
+            return;
+        }
+        self.end_run(out);
+        self.run_byte = byte;
+        self.run_len = 1;
+    }
+
+    /// Emits everything still buffered. The encoder can be used again
+    /// afterwards; the output is valid either way.
+    pub fn finish(&mut self, out: &mut Vec<u8>) {
+        self.end_run(out);
+        self.end_literal(out);
+    }
+
+    fn end_run(&mut self, out: &mut Vec<u8>) {
+        match self.run_len {
+            0 => {}
+            // A single byte is cheaper as part of a literal packet, and so is
+            // a pair that would otherwise split a literal packet in two.
+            n if n == 1 || (n == 2 && !self.literal.is_empty()) => {
+                for _ in 0..n {
+                    self.literal.push(self.run_byte);
+                    if self.literal.len() == MAX_LITERAL {
+                        self.end_literal(out);
+                    }
+                }
+            }
+            n => {
+                self.end_literal(out);
+                out.push((n + 126) as u8);
+                out.push(self.run_byte);
+            }
+        }
+        self.run_len = 0;
+    }
+
+    fn end_literal(&mut self, out: &mut Vec<u8>) {
+        if !self.literal.is_empty() {
+            out.push((self.literal.len() - 1) as u8);
+            out.append(&mut self.literal);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+enum State {
+    #[default]
+    Header,
+    Literal(usize),
+    Run(usize),
+}
+
+/// Decoder state. Input may be split anywhere, even inside a packet.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    state: State,
+}
+
+impl Decoder {
+    pub fn feed(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        let mut input = input;
+        while !input.is_empty() {
+            match self.state {
+                State::Header => {
+                    let header = input[0] as usize;
+                    input = &input[1..];
+                    self.state = if header < 128 {
+                        State::Literal(header + 1)
+                    } else {
+                        State::Run(header - 126)
+                    };
+                }
+                State::Literal(remaining) => {
+                    let n = remaining.min(input.len());
+                    out.extend_from_slice(&input[..n]);
+                    input = &input[n..];
+                    self.state = match remaining - n {
+                        0 => State::Header,
+                        left => State::Literal(left),
+                    };
+                }
+                State::Run(count) => {
+                    out.resize(out.len() + count, input[0]);
+                    input = &input[1..];
+                    self.state = State::Header;
+                }
+            }
+        }
+    }
+
+    /// Checks that the input did not stop in the middle of a packet.
+    pub fn finish(&self) -> io::Result<()> {
+        match self.state {
+            State::Header => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "compressed stream ends inside a packet",
+            )),
+        }
+    }
+}
+
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    let mut out = Vec::new();
+    for &byte in data {
+        encoder.push(byte, &mut out);
+    }
+    encoder.finish(&mut out);
+    out
+}
+
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decoder = Decoder::default();
+    let mut out = Vec::new();
+    decoder.feed(data, &mut out);
+    decoder.finish()?;
+    Ok(out)
+}
+
+/// Compresses everything written to it and passes the result on to `inner`.
+/// Call `shutdown` at the end, or the last packet is never written.
+pub struct RleWriter<W> {
+    inner: W,
+    encoder: Encoder,
+    /// Compressed bytes not yet accepted by `inner`.
+    out: Vec<u8>,
+}
+
+impl<W> RleWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            encoder: Encoder::default(),
+            out: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: AsyncWrite + Unpin> RleWriter<W> {
+    /// Writes out the compressed bytes buffered so far.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.out.is_empty() {
+            let n =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.out))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.out.drain(..n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for RleWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        // Backpressure: accept new data only once the previous output is out,
+        // so the buffer never grows beyond one call's worth.
+        ready!(this.poll_drain(cx))?;
+        for &byte in buf {
+            this.encoder.push(byte, &mut this.out);
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        // Ending the current packets early costs a little compression, but
+        // after a flush the receiver can decode everything written so far.
+        this.encoder.finish(&mut this.out);
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.encoder.finish(&mut this.out);
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// Decompresses everything read from `inner`.
+pub struct RleReader<R> {
+    inner: R,
+    decoder: Decoder,
+    /// Decompressed bytes not yet handed to the caller.
+    out: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+impl<R> RleReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            decoder: Decoder::default(),
+            out: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for RleReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.pos < this.out.len() {
+                let n = buf.remaining().min(this.out.len() - this.pos);
+                buf.put_slice(&this.out[this.pos..this.pos + n]);
+                this.pos += n;
+                return Poll::Ready(Ok(()));
+            }
+            if this.eof {
+                return Poll::Ready(this.decoder.finish());
+            }
+
+            this.out.clear();
+            this.pos = 0;
+            let mut compressed = [0u8; 4096];
+            let mut read_buf = ReadBuf::new(&mut compressed);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read_buf))?;
+            if read_buf.filled().is_empty() {
+                this.eof = true;
+            }
+            // May produce no output, e.g. if only a header byte arrived, so
+            // go around the loop again instead of returning an empty read
+            // that the caller would take for EOF.
+            this.decoder.feed(read_buf.filled(), &mut this.out);
+        }
+    }
+}
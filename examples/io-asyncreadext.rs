@@ -1,12 +1,19 @@
+#[path = "common/temp.rs"]
+mod temp;
+
 use tokio::{fs::File, io::AsyncReadExt};
 
+use temp::TempFile;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Create a dummy file
-    tokio::fs::write("temp_read.txt", "Hello\nAsync World!").await?;
+    // Create a dummy file with a unique name. It is removed when `temp_file`
+    // is dropped, even if one of the `?` below returns early.
+    let temp_file =
+        TempFile::with_contents("read", "Hello\nAsync World!").await?;
 
     // Open the file for reading
-    let mut file = File::open("./temp_read.txt").await?;
+    let mut file = File::open(&temp_file).await?;
 
     // --- Common read methods ---
 
@@ -31,7 +38,7 @@ async fn main() -> anyhow::Result<()> {
     // Note: File cursor is now at the end.
 
     // Re-open file to demonstrate other methods
-    let mut file = File::open("./temp_read.txt").await?;
+    let mut file = File::open(&temp_file).await?;
 
     // 3. read_to_string(): Read all remaining bytes into a String.
     let mut content = String::new();
@@ -42,7 +49,7 @@ async fn main() -> anyhow::Result<()> {
     // Async World!
 
     // Re-open file
-    let mut file = File::open("./temp_read.txt").await?;
+    let mut file = File::open(&temp_file).await?;
 
     // 4. read_exact(): Read exactly N bytes into the buffer.
     //    Returns an error if EOF is reached before N bytes are read.
@@ -60,8 +67,5 @@ async fn main() -> anyhow::Result<()> {
         String::from_utf8(exact_buffer.into()).unwrap()
     );
 
-    // Clean up
-    tokio::fs::remove_file("./temp_read.txt").await?;
-
     Ok(())
 }
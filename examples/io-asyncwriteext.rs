@@ -1,10 +1,19 @@
+#[path = "common/temp.rs"]
+mod temp;
+
 use colored::Colorize;
 use tokio::{fs::File, io::AsyncWriteExt};
 
+use temp::TempDir;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // A unique directory, removed with its contents when `dir` is dropped
+    let dir = TempDir::new("write").await?;
+    let path = dir.join("temp_write.txt");
+
     // Open file for writing (create if not exists, truncate if exists)
-    let mut file = File::create("temp_write.txt").await?;
+    let mut file = File::create(&path).await?;
 
     // --- Common write methods ---
 
@@ -26,15 +35,12 @@ async fn main() -> anyhow::Result<()> {
     println!("Flushed the writer.");
 
     // Verify content (optional)
-    let content = tokio::fs::read_to_string("temp_write.txt").await?;
+    let content = tokio::fs::read_to_string(&path).await?;
     println!(
         "\nFile content:
 =============\n{}",
         content.trim().color("green")
     );
 
-    // Clean up
-    tokio::fs::remove_file("temp_write.txt").await?;
-
     Ok(())
 }
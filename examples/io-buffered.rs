@@ -1,14 +1,23 @@
+#[path = "common/temp.rs"]
+mod temp;
+
 use colored::Colorize;
 use tokio::{
     fs::File,
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
 };
 
+use temp::TempDir;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // A unique directory, removed with its contents when `dir` is dropped
+    let dir = TempDir::new("buffered").await?;
+
     // --- BufReader ---
-    tokio::fs::write("buffered_read.txt", "Line 1\nLine 2\nEnd").await?;
-    let file_reader = File::open("buffered_read.txt").await?;
+    let read_path = dir.join("buffered_read.txt");
+    tokio::fs::write(&read_path, "Line 1\nLine 2\nEnd").await?;
+    let file_reader = File::open(&read_path).await?;
     let mut reader = BufReader::new(file_reader); // Wrap the file reader
 
     let mut line_buffer = String::new();
@@ -22,7 +31,8 @@ async fn main() -> anyhow::Result<()> {
     println!("\nFinished reading lines.");
 
     // --- BufWriter ---
-    let file_writer = File::create("buffered_write.txt").await?;
+    let write_path = dir.join("buffered_write.txt");
+    let file_writer = File::create(&write_path).await?;
     let mut writer = BufWriter::new(file_writer); // Wrap the file writer
 
     writer.write_all(b"Buffered write 1.").await?;
@@ -44,12 +54,8 @@ async fn main() -> anyhow::Result<()> {
     println!("BufWriter shutdown (implicit flush).");
 
     // Verify content
-    let content = tokio::fs::read_to_string("buffered_write.txt").await?;
+    let content = tokio::fs::read_to_string(&write_path).await?;
     println!("Buffered write file content:\n{}", content.color("green"));
 
-    // Clean up
-    tokio::fs::remove_file("buffered_read.txt").await?;
-    tokio::fs::remove_file("buffered_write.txt").await?;
-
     Ok(())
 }
@@ -1,25 +1,30 @@
+#[path = "common/temp.rs"]
+mod temp;
+
 use tokio::{fs::{self, File}, io};
 
+use temp::TempDir;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Create source and destination files
+    // Create source and destination files in a unique temporary directory
+    let dir = TempDir::new("copy").await?;
     let source_content = b"Data to be copied asynchronously.";
-    tokio::fs::write("source.txt", source_content).await?;
-    let mut source_file = File::open("source.txt").await?;
-    let mut dest_file = File::create("destination.txt").await?;
+    tokio::fs::write(dir.join("source.txt"), source_content).await?;
+    let mut source_file = File::open(dir.join("source.txt")).await?;
+    let mut dest_file = File::create(dir.join("destination.txt")).await?;
 
     println!("Starting copy...");
     let bytes_copied = io::copy(&mut source_file, &mut dest_file).await?;
     println!("Copied {} bytes.", bytes_copied);
 
     // Verify
-    let dest_content = fs::read("destination.txt").await?;
+    let dest_content = fs::read(dir.join("destination.txt")).await?;
     assert_eq!(source_content, &dest_content[..]);
     println!("Copy verified.");
 
-    // Clean up
-    fs::remove_file("source.txt").await?;
-    fs::remove_file("destination.txt").await?;
+    // Clean up explicitly, so errors are reported instead of ignored on drop
+    dir.close().await?;
 
     Ok(())
 }
@@ -0,0 +1,179 @@
+mod mock;
+
+use colored::Colorize;
+use tokio::io::{
+    self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ErrorKind,
+};
+
+use mock::Builder;
+
+// --- The code under test: length-prefixed frames ---
+
+async fn write_frame<W>(writer: &mut W, payload: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer.write_u16(payload.len() as u16).await?;
+    writer.write_all(payload).await
+}
+
+/// Looks right and passes every test against a local file, but a single
+/// `write` may write only part of the payload. Clippy knows this mistake
+/// too, hence the `allow`.
+#[allow(clippy::unused_io_amount)]
+async fn write_frame_buggy<W>(writer: &mut W, payload: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer.write_u16(payload.len() as u16).await?;
+    writer.write(payload).await?;
+    Ok(())
+}
+
+async fn read_frame<R>(reader: &mut R) -> io::Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin,
+{
+    let len = reader.read_u16().await?;
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload).await?;
+    Ok(payload)
+}
+
+async fn write_vs_write_all() -> io::Result<()> {
+    // The peer accepts only 3 bytes on the first write.
+    let mut mock = Builder::new().write(b"Hel").write(b"lo, world").build();
+    let n = mock.write(b"Hello, world").await?;
+    assert_eq!(n, 3);
+    // It is up to the caller to write the rest...
+    mock.write_all(&b"Hello, world"[n..]).await?;
+    assert!(mock.is_done());
+
+    // ...which `write_all` does, also when the writer is not ready in
+    // between.
+    let mut mock = Builder::new()
+        .write(b"He")
+        .pending()
+        .write(b"llo")
+        .pending()
+        .write(b"!")
+        .build();
+    mock.write_all(b"Hello!").await?;
+    assert!(mock.is_done());
+    assert_eq!(mock.pendings(), 2);
+
+    println!(
+        "{}",
+        "write returns short counts, write_all retries".green()
+    );
+    Ok(())
+}
+
+/// Splits the frame into two writes at every possible position.
+async fn short_writes_everywhere() -> io::Result<()> {
+    let payload = b"hello frame";
+    let mut frame = (payload.len() as u16).to_be_bytes().to_vec();
+    frame.extend_from_slice(payload);
+
+    let mut buggy_failures = 0;
+    for split in 1..frame.len() {
+        let script = || {
+            Builder::new()
+                .write(&frame[..split])
+                .pending()
+                .write(&frame[split..])
+        };
+
+        let mut mock = script().build();
+        write_frame(&mut mock, payload).await?;
+        assert!(mock.is_done(), "split at {}", split);
+        assert_eq!(mock.written(), frame);
+
+        let mut mock = script().build();
+        write_frame_buggy(&mut mock, payload).await?;
+        if !mock.is_done() {
+            buggy_failures += 1;
+            assert!(mock.written().len() < frame.len());
+        }
+    }
+    // Splits inside the length prefix are handled by `write_u16`; every
+    // split inside the payload loses data in the buggy version.
+    assert_eq!(buggy_failures, payload.len() - 1);
+    println!(
+        "{} write_frame ok at all {} splits, the buggy version loses data \
+         at {}",
+        "short writes:".green(),
+        frame.len() - 1,
+        buggy_failures
+    );
+    Ok(())
+}
+
+/// Delivers the frames one byte per read, with `Pending` before each byte.
+async fn short_reads_everywhere() -> io::Result<()> {
+    let mut builder = Builder::new();
+    for byte in b"\x00\x05hello\x00\x00\x00\x03bye" {
+        builder = builder.pending().read(&[*byte]);
+    }
+    let mut mock = builder.build();
+    assert_eq!(read_frame(&mut mock).await?, b"hello");
+    assert_eq!(read_frame(&mut mock).await?, b"");
+    assert_eq!(read_frame(&mut mock).await?, b"bye");
+    assert!(mock.is_done());
+    // The script is used up: EOF.
+    let err = read_frame(&mut mock).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    println!(
+        "{} 3 frames read byte by byte, {} times Pending",
+        "short reads:".green(),
+        mock.pendings()
+    );
+    Ok(())
+}
+
+async fn injected_errors() -> io::Result<()> {
+    // The connection drops in the middle of a frame.
+    let mut mock = Builder::new()
+        .read(b"\x00\x05he")
+        .read_error(ErrorKind::ConnectionReset)
+        .build();
+    let err = read_frame(&mut mock).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+
+    // EOF in the middle of a frame is an error too.
+    let mut mock = Builder::new().read(b"\x00\x05he").build();
+    let err = read_frame(&mut mock).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+    // An `AsyncRead` must return `Pending` when it has no data. `WouldBlock`
+    // is not retried by Tokio; it reaches the caller as an ordinary error.
+    let mut mock = Builder::new()
+        .read(b"\x00")
+        .read_error(ErrorKind::WouldBlock)
+        .read(b"\x01x")
+        .build();
+    let err = read_frame(&mut mock).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+
+    // A failed `write_all` may already have written part of the data.
+    let mut mock = Builder::new()
+        .write(b"\x00\x05hel")
+        .write_error(ErrorKind::ConnectionReset)
+        .build();
+    let err = write_frame(&mut mock, b"hello").await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+    assert_eq!(mock.written(), b"\x00\x05hel");
+    assert!(mock.is_done());
+
+    println!("{}", "injected errors reach the caller".green());
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    write_vs_write_all().await?;
+    short_writes_everywhere().await?;
+    short_reads_everywhere().await?;
+    injected_errors().await?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::VecDeque,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+#[derive(Debug)]
+enum Action {
+    Read(Vec<u8>),
+    Write(Vec<u8>),
+    Pending,
+    ReadError(io::ErrorKind),
+    WriteError(io::ErrorKind),
+}
+
+/// Builds a [`Mock`] from a script of actions, played back in order.
+#[derive(Debug, Default)]
+pub struct Builder {
+    actions: VecDeque<Action>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The next reads return these bytes: all of them if the caller's buffer
+    /// is big enough, otherwise the rest is returned by the following reads.
+    pub fn read(mut self, data: &[u8]) -> Self {
+        self.actions.push_back(Action::Read(data.to_vec()));
+        self
+    }
+
+    /// The next writes must be these bytes. A single write accepts at most
+    /// `data.len()` bytes, so `write(b"ab").write(b"cd")` turns a write of
+    /// `b"abcd"` into a short write of 2 bytes.
+    pub fn write(mut self, data: &[u8]) -> Self {
+        self.actions.push_back(Action::Write(data.to_vec()));
+        self
+    }
+
+    /// The next read or write returns `Pending` once. It wakes the task right
+    /// away, like a socket that becomes ready again immediately.
+    pub fn pending(mut self) -> Self {
+        self.actions.push_back(Action::Pending);
+        self
+    }
+
+    pub fn read_error(mut self, kind: io::ErrorKind) -> Self {
+        self.actions.push_back(Action::ReadError(kind));
+        self
+    }
+
+    pub fn write_error(mut self, kind: io::ErrorKind) -> Self {
+        self.actions.push_back(Action::WriteError(kind));
+        self
+    }
+
+    pub fn build(self) -> Mock {
+        Mock {
+            actions: self.actions,
+            written: Vec::new(),
+            pendings: 0,
+        }
+    }
+}
+
+/// A scripted `AsyncRead + AsyncWrite`. Everything happens in memory and in
+/// exactly the order of the script, so a test sees the same short reads,
+/// short writes and errors on every run.
+///
+/// Reading when the script expects a write, or writing other bytes than
+/// expected, panics: that is a bug in the code under test. Once the script
+/// is used up, reads return EOF.
+#[derive(Debug)]
+pub struct Mock {
+    actions: VecDeque<Action>,
+    written: Vec<u8>,
+    pendings: usize,
+}
+
+impl Mock {
+    /// All bytes accepted by writes so far.
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// How many times `Pending` was returned.
+    pub fn pendings(&self) -> usize {
+        self.pendings
+    }
+
+    /// Whether the whole script has been played.
+    pub fn is_done(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    fn pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.actions.pop_front();
+        self.pendings += 1;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+impl AsyncRead for Mock {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.actions.front_mut() {
+            // Script finished: EOF.
+            None => Poll::Ready(Ok(())),
+            Some(Action::Read(data)) => {
+                let n = data.len().min(buf.remaining());
+                buf.put_slice(&data[..n]);
+                data.drain(..n);
+                if data.is_empty() {
+                    this.actions.pop_front();
+                }
+                Poll::Ready(Ok(()))
+            }
+            Some(Action::Pending) => this.pending(cx),
+            Some(Action::ReadError(kind)) => {
+                let kind = *kind;
+                this.actions.pop_front();
+                Poll::Ready(Err(kind.into()))
+            }
+            Some(action) => panic!("mock: read, but expected {:?}", action),
+        }
+    }
+}
+
+impl AsyncWrite for Mock {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        match this.actions.front_mut() {
+            Some(Action::Write(expected)) => {
+                let n = expected.len().min(buf.len());
+                assert_eq!(
+                    &buf[..n],
+                    &expected[..n],
+                    "mock: wrote {:?}, expected {:?}",
+                    String::from_utf8_lossy(&buf[..n]),
+                    String::from_utf8_lossy(&expected[..n]),
+                );
+                this.written.extend_from_slice(&buf[..n]);
+                expected.drain(..n);
+                if expected.is_empty() {
+                    this.actions.pop_front();
+                }
+                Poll::Ready(Ok(n))
+            }
+            Some(Action::Pending) => this.pending(cx).map_ok(|()| 0),
+            Some(Action::WriteError(kind)) => {
+                let kind = *kind;
+                this.actions.pop_front();
+                Poll::Ready(Err(kind.into()))
+            }
+            action => panic!(
+                "mock: wrote {:?}, but expected {:?}",
+                String::from_utf8_lossy(buf),
+                action
+            ),
+        }
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,209 @@
+mod parse;
+mod pipeline;
+#[path = "../common/temp.rs"]
+mod temp;
+
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+use rand::Rng;
+use serde_json::{Value, json};
+use tokio::{
+    fs::File,
+    io::{self, AsyncWriteExt, BufReader},
+    time::sleep,
+};
+
+use parse::{Format, Record, RecordReader, parse_csv_line};
+use pipeline::process;
+use temp::TempDir;
+
+/// Looks up a field that must be a number.
+fn number(record: &Record, name: &str) -> Result<f64, String> {
+    match record.fields.get(name) {
+        Some(Value::Number(n)) => Ok(n.as_f64().unwrap()),
+        Some(other) => Err(format!("{} is not a number: {}", name, other)),
+        None => Err(format!("{} is missing", name)),
+    }
+}
+
+/// An example transform. The random sleep stands in for real async work,
+/// such as a database lookup, and makes transforms finish out of order.
+async fn order_total(record: Record) -> Result<Option<Value>, String> {
+    let delay = rand::rng().random_range(0..5);
+    sleep(Duration::from_millis(delay)).await;
+    let quantity = number(&record, "qty")?;
+    if quantity == 0.0 {
+        return Ok(None);
+    }
+    let total = number(&record, "price")? * quantity;
+    let id = record.fields.get("id").ok_or("id is missing")?;
+    Ok(Some(json!({ "id": id, "total": total })))
+}
+
+async fn json_lines_demo(dir: &TempDir) -> anyhow::Result<()> {
+    let input = dir.join("orders.jsonl");
+    let mut lines: Vec<u8> = concat!(
+        r#"{"id": 1, "item": "apple", "price": 0.5, "qty": 10}"#,
+        "\n",
+        r#"{"id": 2, "item": "pear", "price": 0.75, "qty": 0}"#,
+        "\n",
+        r#"{"id": 3, "item": "plum", "price": "cheap", "qty": 3}"#,
+        "\n",
+        "not json\n",
+        "[1, 2, 3]\n",
+        "\n",
+        r#"{"id": 7, "item": "fig", "price": 2, "qty": 4}"#,
+        "\r\n",
+    )
+    .into();
+    lines.extend_from_slice(b"\xff\xfe\n");
+    lines
+        .extend_from_slice(b"{\"item\": \"lime\", \"price\": 1, \"qty\": 2}\n");
+    // The last line has no line break.
+    lines.extend_from_slice(br#"{"id": 9, "item": "kiwi", "qty": 1}"#);
+    tokio::fs::write(&input, &lines).await?;
+
+    let output = dir.join("totals.jsonl");
+    let reader = RecordReader::new(
+        BufReader::new(File::open(&input).await?),
+        Format::JsonLines,
+    );
+    let report =
+        process(reader, File::create(&output).await?, 4, order_total).await?;
+
+    for error in &report.errors {
+        println!("  {}", error.to_string().red());
+    }
+    let lines: Vec<usize> = report.errors.iter().map(|e| e.line).collect();
+    assert_eq!(lines, [3, 4, 5, 8, 9, 10]);
+    assert!(report.errors[0].message.contains("price is not a number"));
+    assert_eq!(report.errors[4].message, "id is missing");
+    assert!(report.errors[5].message.contains("price is missing"));
+    assert_eq!((report.records, report.written, report.skipped), (6, 2, 1));
+
+    let written = tokio::fs::read_to_string(&output).await?;
+    assert_eq!(
+        written,
+        "{\"id\":1,\"total\":5.0}\n{\"id\":7,\"total\":8.0}\n"
+    );
+    println!("{}\n{}", "JSON lines output:".green(), written.trim_end());
+    Ok(())
+}
+
+async fn csv_demo() -> anyhow::Result<()> {
+    assert_eq!(
+        parse_csv_line(r#"a,"b, c","say ""hi""",,"#).unwrap(),
+        ["a", "b, c", r#"say "hi""#, "", ""]
+    );
+
+    let input = concat!(
+        "id,name,city,score\n",
+        "1,Alice,\"Paris, France\",90\n",
+        "2,\"Bob \"\"the builder\"\"\",London,75\n",
+        "3,Carol,Berlin\n",
+        "4,\"Dave,Rome,80\n",
+        "5,Eve,\"Oslo\"x,70\n",
+        "6,Frank,Madrid,88\n",
+    );
+    // Any `AsyncBufRead` will do, including a byte slice.
+    let reader = RecordReader::new(input.as_bytes(), Format::Csv);
+    let mut output = Vec::new();
+    let report = process(reader, &mut output, 2, |record| async move {
+        Ok(Some(Value::Object(record.fields)))
+    })
+    .await?;
+
+    for error in &report.errors {
+        println!("  {}", error.to_string().red());
+    }
+    let lines: Vec<usize> = report.errors.iter().map(|e| e.line).collect();
+    assert_eq!(lines, [4, 5, 6]);
+    assert_eq!(report.errors[0].message, "expected 4 fields, found 3");
+    assert_eq!(report.errors[1].message, "unterminated quoted field");
+
+    let output = String::from_utf8(output)?;
+    let records: Vec<Value> = output
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0]["city"], "Paris, France");
+    assert_eq!(records[1]["name"], r#"Bob "the builder""#);
+    assert_eq!(records[2]["score"], 88);
+    println!("{}\n{}", "CSV as JSON lines:".green(), output.trim_end());
+
+    // A concurrency of 0 runs one transform at a time.
+    let reader = RecordReader::new(input.as_bytes(), Format::Csv);
+    let report = process(reader, io::sink(), 0, |record| async move {
+        Ok(Some(Value::Object(record.fields)))
+    })
+    .await?;
+    assert_eq!(report.written, 3);
+
+    // A bad header leaves no column names to read the rows with: it ends the
+    // stream, rather than the first row being taken as the header.
+    let input = "id,\"name\nBroken,1\n2,Grace\n";
+    let reader = RecordReader::new(input.as_bytes(), Format::Csv);
+    let err = process(reader, io::sink(), 2, |record| async move {
+        Ok(Some(Value::Object(record.fields)))
+    })
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("line 1"), "{}", err);
+    Ok(())
+}
+
+/// Streams records from a producer task through a pipe, so input is parsed
+/// while it is still being written.
+async fn run_streaming(count: usize, concurrency: usize) -> anyhow::Result<()> {
+    let (mut tx, rx) = io::duplex(1024);
+    let producer = tokio::spawn(async move {
+        for id in 0..count {
+            let line = format!(
+                "{{\"id\": {}, \"price\": 1.5, \"qty\": {}}}\n",
+                id,
+                id + 1
+            );
+            tx.write_all(line.as_bytes()).await?;
+        }
+        io::Result::Ok(())
+    });
+
+    let reader = RecordReader::new(BufReader::new(rx), Format::JsonLines);
+    let mut output = Vec::new();
+    let report = process(reader, &mut output, concurrency, order_total).await?;
+    producer.await??;
+
+    assert!(report.errors.is_empty());
+    assert_eq!(report.written, count);
+    // Results come back in input order, however the transforms interleave.
+    for (id, line) in String::from_utf8(output)?.lines().enumerate() {
+        let value: Value = serde_json::from_str(line)?;
+        assert_eq!(value["id"], id);
+    }
+    Ok(())
+}
+
+async fn parallelism_demo() -> anyhow::Result<()> {
+    for concurrency in [1, 16] {
+        let start = Instant::now();
+        run_streaming(200, concurrency).await?;
+        println!(
+            "{} 200 records, concurrency {:>2}: {:?}",
+            "streamed".green(),
+            concurrency,
+            start.elapsed()
+        );
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let dir = TempDir::new("records").await?;
+    json_lines_demo(&dir).await?;
+    csv_demo().await?;
+    parallelism_demo().await
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+
+use serde_json::{Map, Value};
+use tokio::io::{self, AsyncBufRead, AsyncBufReadExt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON object per line.
+    JsonLines,
+    /// Comma separated values, the first line holds the column names.
+    /// Fields may be quoted, with `""` for a quote, but may not contain line
+    /// breaks.
+    Csv,
+}
+
+/// A parsed record and the line it came from.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub line: usize,
+    pub fields: Map<String, Value>,
+}
+
+/// A problem with one line. The stream carries on with the next line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Reads records one line at a time from any `AsyncBufRead`, so the input
+/// is never held in memory as a whole.
+pub struct RecordReader<R> {
+    reader: R,
+    format: Format,
+    line: usize,
+    buf: Vec<u8>,
+    /// CSV column names, once the header line has been read.
+    header: Option<Vec<String>>,
+}
+
+impl<R: AsyncBufRead + Unpin> RecordReader<R> {
+    pub fn new(reader: R, format: Format) -> Self {
+        Self {
+            reader,
+            format,
+            line: 0,
+            buf: Vec::new(),
+            header: None,
+        }
+    }
+
+    /// The next record or malformed line, `None` at the end of the input.
+    /// Only an I/O error ends the stream early, or a CSV header that cannot
+    /// be parsed: without column names, no line after it can be read.
+    pub async fn next(
+        &mut self,
+    ) -> io::Result<Option<Result<Record, LineError>>> {
+        loop {
+            self.buf.clear();
+            // `read_until` instead of `read_line`: invalid UTF-8 is a problem
+            // of this line only, not an I/O error that ends the stream.
+            if self.reader.read_until(b'\n', &mut self.buf).await? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            let line = self.line;
+            let error = |message: String| LineError { line, message };
+
+            let Ok(text) = std::str::from_utf8(&self.buf) else {
+                return Ok(Some(Err(error("invalid UTF-8".into()))));
+            };
+            let text = text.trim_end_matches(['\n', '\r']);
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let fields = match self.format {
+                Format::JsonLines => parse_json(text),
+                Format::Csv => match &self.header {
+                    None => match parse_csv_line(text) {
+                        Ok(header) => {
+                            self.header = Some(header);
+                            continue;
+                        }
+                        Err(message) => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!(
+                                    "invalid CSV header: {}",
+                                    error(message)
+                                ),
+                            ));
+                        }
+                    },
+                    Some(header) => csv_record(header, text),
+                },
+            };
+            let record = fields.map(|fields| Record { line, fields });
+            return Ok(Some(record.map_err(error)));
+        }
+    }
+}
+
+fn parse_json(text: &str) -> Result<Map<String, Value>, String> {
+    match serde_json::from_str(text) {
+        Ok(Value::Object(fields)) => Ok(fields),
+        Ok(other) => Err(format!("expected an object, found {}", other)),
+        Err(err) => Err(format!("invalid JSON: {}", err)),
+    }
+}
+
+fn csv_record(
+    header: &[String],
+    text: &str,
+) -> Result<Map<String, Value>, String> {
+    let values = parse_csv_line(text)?;
+    if values.len() != header.len() {
+        return Err(format!(
+            "expected {} fields, found {}",
+            header.len(),
+            values.len()
+        ));
+    }
+    Ok(header
+        .iter()
+        .cloned()
+        .zip(values.into_iter().map(csv_value))
+        .collect())
+}
+
+/// Numbers become JSON numbers, everything else stays a string.
+fn csv_value(field: String) -> Value {
+    if let Ok(n) = field.parse::<i64>() {
+        Value::from(n)
+    } else if let Ok(x) = field.parse::<f64>()
+        && x.is_finite()
+    {
+        Value::from(x)
+    } else {
+        Value::String(field)
+    }
+}
+
+/// Splits one CSV line into fields.
+pub fn parse_csv_line(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().enumerate().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.next_if(|&(_, c)| c == '"').is_some() {
+            loop {
+                match chars.next() {
+                    None => return Err("unterminated quoted field".into()),
+                    Some((_, '"')) => {
+                        // `""` inside quotes is a literal quote.
+                        if chars.next_if(|&(_, c)| c == '"').is_none() {
+                            break;
+                        }
+                        field.push('"');
+                    }
+                    Some((_, c)) => field.push(c),
+                }
+            }
+            match chars.peek() {
+                None | Some((_, ',')) => {}
+                Some((column, _)) => {
+                    return Err(format!(
+                        "expected ',' after quoted field at column {}",
+                        column + 1
+                    ));
+                }
+            }
+        } else {
+            while let Some((_, c)) = chars.next_if(|&(_, c)| c != ',') {
+                field.push(c);
+            }
+        }
+        fields.push(field);
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
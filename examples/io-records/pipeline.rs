@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+
+use serde_json::Value;
+use tokio::{
+    io::{self, AsyncBufRead, AsyncWrite, AsyncWriteExt, BufWriter},
+    task::JoinHandle,
+};
+
+use crate::parse::{LineError, Record, RecordReader};
+
+/// What happened to the input, once the stream is done.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub records: usize,
+    pub written: usize,
+    /// Records the transform chose to drop.
+    pub skipped: usize,
+    /// Malformed lines and failed transforms, in line order.
+    pub errors: Vec<LineError>,
+}
+
+/// A transform in flight, along with the line its record came from.
+type InFlight = (usize, JoinHandle<Result<Option<Value>, String>>);
+
+/// Streams records from `reader` through `transform` into `writer`, one
+/// JSON line per result.
+///
+/// Up to `concurrency` transforms run at the same time, as separate tasks,
+/// but results are written in input order; a `concurrency` of 0 counts as 1.
+/// A transform returns `Ok(None)` to drop a record and `Err` to report it;
+/// neither stops the stream, and neither does a malformed line. Only the
+/// errors of `RecordReader::next` do.
+pub async fn process<R, W, F, Fut>(
+    mut reader: RecordReader<R>,
+    writer: W,
+    concurrency: usize,
+    mut transform: F,
+) -> io::Result<Report>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+    F: FnMut(Record) -> Fut,
+    Fut: Future<Output = Result<Option<Value>, String>> + Send + 'static,
+{
+    let concurrency = concurrency.max(1);
+    let mut writer = BufWriter::new(writer);
+    let mut report = Report::default();
+    let mut in_flight: VecDeque<InFlight> = VecDeque::new();
+
+    while let Some(parsed) = reader.next().await? {
+        match parsed {
+            Ok(record) => {
+                report.records += 1;
+                // Bounded parallelism: wait for the oldest transform before
+                // starting another one. This also keeps memory bounded when
+                // the input is much faster than the transform.
+                if in_flight.len() == concurrency {
+                    let oldest = in_flight.pop_front().unwrap();
+                    finish(oldest, &mut writer, &mut report).await?;
+                }
+                let line = record.line;
+                in_flight.push_back((line, tokio::spawn(transform(record))));
+            }
+            Err(error) => report.errors.push(error),
+        }
+    }
+    while let Some(oldest) = in_flight.pop_front() {
+        finish(oldest, &mut writer, &mut report).await?;
+    }
+    // `BufWriter` holds the last few results until it is flushed.
+    writer.flush().await?;
+
+    report.errors.sort_by_key(|error| error.line);
+    Ok(report)
+}
+
+async fn finish<W>(
+    (line, handle): InFlight,
+    writer: &mut BufWriter<W>,
+    report: &mut Report,
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let error = |message| LineError { line, message };
+    match handle.await {
+        Ok(Ok(Some(value))) => {
+            let mut json = value.to_string();
+            json.push('\n');
+            writer.write_all(json.as_bytes()).await?;
+            report.written += 1;
+        }
+        Ok(Ok(None)) => report.skipped += 1,
+        Ok(Err(message)) => report.errors.push(error(message)),
+        // A panicking transform is a failed record too.
+        Err(err) => report.errors.push(error(err.to_string())),
+    }
+    Ok(())
+}
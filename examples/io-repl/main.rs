@@ -0,0 +1,124 @@
+mod repl;
+
+use std::time::Duration;
+
+use anyhow::Context;
+use tokio::{
+    io::{self, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::mpsc,
+    time::sleep,
+};
+
+use repl::Repl;
+
+/// Registers the example commands on a REPL, whatever its input and output are.
+fn with_commands<R, W>(repl: Repl<R, W>) -> Repl<R, W>
+where
+    R: io::AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    repl.command("echo", "print the arguments", |args| async move {
+        Ok(args.join(" "))
+    })
+    .command("add", "add up integers: add 1 2 3", |args| async move {
+        let mut sum = 0i64;
+        for arg in &args {
+            sum += arg
+                .parse::<i64>()
+                .with_context(|| format!("not a number: {}", arg))?;
+        }
+        Ok(sum.to_string())
+    })
+    .command(
+        "sleep",
+        "sleep for the given milliseconds",
+        |args| async move {
+            let ms: u64 = args.first().context("usage: sleep <ms>")?.parse()?;
+            sleep(Duration::from_millis(ms)).await;
+            Ok(format!("slept {}ms", ms))
+        },
+    )
+}
+
+/// Drives the REPL over in-memory `duplex` pipes instead of a terminal: we
+/// type into one end and read what the REPL prints from the other.
+async fn scripted_session() -> anyhow::Result<()> {
+    let (mut keyboard, repl_input) = io::duplex(1024);
+    let (repl_output, mut screen) = io::duplex(64 * 1024);
+    let (ctrl_c, interrupts) = mpsc::channel(1);
+
+    let repl = with_commands(Repl::new(
+        BufReader::new(repl_input),
+        repl_output,
+        interrupts,
+    ));
+    let session = tokio::spawn(repl.run());
+
+    keyboard.write_all(b"echo \"hello world\" !\n").await?;
+    keyboard.write_all(b"add 1 2 39\n").await?;
+    keyboard.write_all(b"add 1 two\n").await?;
+    keyboard.write_all(b"frobnicate\n").await?;
+    keyboard.write_all(b"sleep 60000\n").await?;
+    // Give the REPL a moment to start the (very long) sleep, then hit Ctrl-C.
+    sleep(Duration::from_millis(100)).await;
+    ctrl_c.send(()).await?;
+    keyboard.write_all(b"!1\n").await?;
+    keyboard.write_all(b"history\n").await?;
+    // Closing our end of the input is the equivalent of Ctrl-D.
+    drop(keyboard);
+
+    let history = session.await??;
+    let mut transcript = String::new();
+    screen.read_to_string(&mut transcript).await?;
+    println!("{}", transcript);
+
+    assert!(transcript.contains("repl[1]> hello world !\n"));
+    assert!(transcript.contains("repl[2]> 42\n"));
+    assert!(transcript.contains("error: not a number: two"));
+    assert!(transcript.contains("unknown command: frobnicate"));
+    assert!(transcript.contains("^C sleep cancelled"));
+    assert_eq!(
+        history,
+        [
+            "echo \"hello world\" !",
+            "add 1 2 39",
+            "add 1 two",
+            "frobnicate",
+            "sleep 60000",
+            "echo \"hello world\" !",
+            "history",
+        ]
+    );
+    // The REPL survived the Ctrl-C and kept going.
+    assert!(transcript.contains("   6  echo \"hello world\" !\n"));
+
+    Ok(())
+}
+
+/// The same REPL on the real stdin/stdout, with Ctrl-C delivered by
+/// `tokio::signal::ctrl_c`.
+async fn interactive_session() -> anyhow::Result<()> {
+    let (ctrl_c, interrupts) = mpsc::channel(1);
+    tokio::spawn(async move {
+        // Once a handler is installed, Ctrl-C no longer terminates the process.
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if ctrl_c.send(()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let repl = Repl::new(BufReader::new(io::stdin()), io::stdout(), interrupts);
+    with_commands(repl).run().await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // cargo run --example io-repl -- --interactive
+    if std::env::args().any(|arg| arg == "--interactive") {
+        interactive_session().await
+    } else {
+        scripted_session().await
+    }
+}
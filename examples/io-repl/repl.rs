@@ -0,0 +1,202 @@
+use std::{collections::BTreeMap, future::Future, pin::Pin};
+
+use tokio::{
+    io::{self, AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+};
+
+type BoxFuture = Pin<Box<dyn Future<Output = anyhow::Result<String>> + Send>>;
+type Handler = Box<dyn Fn(Vec<String>) -> BoxFuture + Send + Sync>;
+
+struct Command {
+    help: String,
+    handler: Handler,
+}
+
+/// Splits a line into words. Double quotes group words containing spaces:
+/// `echo "hello world" !` -> `["echo", "hello world", "!"]`.
+pub fn parse(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut in_quotes = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_quotes {
+        return Err("unterminated quote".to_string());
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// A line-oriented read-eval-print loop over any buffered reader and writer,
+/// so it can run on stdin/stdout as well as on in-memory streams.
+pub struct Repl<R, W> {
+    input: R,
+    output: W,
+    /// Each message is one Ctrl-C. It cancels the running command, or the
+    /// current input line when idle, but never the REPL itself.
+    interrupts: mpsc::Receiver<()>,
+    commands: BTreeMap<String, Command>,
+    history: Vec<String>,
+}
+
+impl<R, W> Repl<R, W>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(input: R, output: W, interrupts: mpsc::Receiver<()>) -> Self {
+        Self {
+            input,
+            output,
+            interrupts,
+            commands: BTreeMap::new(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn command<F, Fut>(mut self, name: &str, help: &str, handler: F) -> Self
+    where
+        F: Fn(Vec<String>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<String>> + Send + 'static,
+    {
+        let command = Command {
+            help: help.to_string(),
+            handler: Box::new(move |args| Box::pin(handler(args))),
+        };
+        self.commands.insert(name.to_string(), command);
+        self
+    }
+
+    async fn print(&mut self, text: &str) -> io::Result<()> {
+        self.output.write_all(text.as_bytes()).await?;
+        self.output.flush().await
+    }
+
+    /// Runs until `exit` or end of input and returns the command history.
+    pub async fn run(mut self) -> io::Result<Vec<String>> {
+        let mut line = String::new();
+
+        loop {
+            let prompt = format!("repl[{}]> ", self.history.len() + 1);
+            self.print(&prompt).await?;
+
+            line.clear();
+            let n = tokio::select! {
+                n = self.input.read_line(&mut line) => n?,
+                Some(()) = self.interrupts.recv() => {
+                    // Ctrl-C at the prompt discards the line, like a shell.
+                    self.print("^C\n").await?;
+                    continue;
+                }
+            };
+            if n == 0 {
+                self.print("\n").await?;
+                break; // EOF (Ctrl-D)
+            }
+
+            let mut entry = line.trim().to_string();
+            // `!3` re-runs the third history entry.
+            if let Some(index) = entry.strip_prefix('!') {
+                let previous = index
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| i.checked_sub(1))
+                    .and_then(|i| self.history.get(i));
+                match previous {
+                    Some(previous) => entry = previous.clone(),
+                    None => {
+                        self.print(&format!("no history entry {}\n", index))
+                            .await?;
+                        continue;
+                    }
+                }
+            }
+
+            let words = match parse(&entry) {
+                Ok(words) if words.is_empty() => continue,
+                Ok(words) => words,
+                Err(err) => {
+                    self.print(&format!("parse error: {}\n", err)).await?;
+                    continue;
+                }
+            };
+            self.history.push(entry);
+
+            let (name, args) = (words[0].as_str(), words[1..].to_vec());
+            match name {
+                "exit" | "quit" => break,
+                "help" => {
+                    let mut help = String::from(
+                        "help\n  show this message\n\
+                         history\n  list previous commands (`!n` re-runs entry n)\n\
+                         exit\n  leave the REPL\n",
+                    );
+                    for (name, command) in &self.commands {
+                        help.push_str(&format!(
+                            "{}\n  {}\n",
+                            name, command.help
+                        ));
+                    }
+                    self.print(&help).await?;
+                }
+                "history" => {
+                    let mut listing = String::new();
+                    for (i, entry) in self.history.iter().enumerate() {
+                        listing.push_str(&format!("{:>4}  {}\n", i + 1, entry));
+                    }
+                    self.print(&listing).await?;
+                }
+                _ => self.execute(name, args).await?,
+            }
+        }
+
+        Ok(self.history)
+    }
+
+    async fn execute(
+        &mut self,
+        name: &str,
+        args: Vec<String>,
+    ) -> io::Result<()> {
+        let Some(command) = self.commands.get(name) else {
+            return self
+                .print(&format!("unknown command: {} (try `help`)\n", name))
+                .await;
+        };
+
+        // Racing the handler against the next interrupt drops (and thereby
+        // cancels) the handler's future on Ctrl-C.
+        let result = tokio::select! {
+            result = (command.handler)(args) => Some(result),
+            Some(()) = self.interrupts.recv() => None,
+        };
+
+        match result {
+            Some(Ok(output)) if output.is_empty() => Ok(()),
+            Some(Ok(output)) => self.print(&format!("{}\n", output)).await,
+            Some(Err(err)) => self.print(&format!("error: {:#}\n", err)).await,
+            None => self.print(&format!("^C {} cancelled\n", name)).await,
+        }
+    }
+}
@@ -0,0 +1,169 @@
+mod records;
+mod sparse;
+#[path = "../common/temp.rs"]
+mod temp;
+
+use std::{io::SeekFrom, os::unix::fs::MetadataExt};
+
+use colored::Colorize;
+use rand::Rng;
+use tokio::{fs::OpenOptions, io::AsyncSeekExt};
+
+use records::{Record, RecordFile, read_at, write_at};
+use sparse::SparseFileBuilder;
+use temp::{TempDir, TempFile};
+
+const RECORDS: u64 = 1_000_000;
+
+async fn positional_io_demo() -> anyhow::Result<()> {
+    let temp = TempFile::with_contents("positional", "0123456789").await?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&temp)
+        .await?;
+    let mut buf = [0u8; 4];
+
+    // Inside the file, at its very end, and past the end.
+    assert_eq!(read_at(&mut file, 0, &mut buf).await?, 4);
+    assert_eq!(&buf, b"0123");
+    assert_eq!(read_at(&mut file, 8, &mut buf).await?, 2);
+    assert_eq!(&buf[..2], b"89");
+    assert_eq!(read_at(&mut file, 10, &mut buf).await?, 0);
+    assert_eq!(read_at(&mut file, 1000, &mut buf).await?, 0);
+
+    // Overwrite in the middle, then write past the end leaving a gap.
+    write_at(&mut file, 3, b"xy").await?;
+    write_at(&mut file, 12, b"ab").await?;
+    let content = tokio::fs::read(&temp).await?;
+    assert_eq!(content, b"012xy56789\0\0ab");
+
+    // Neither moves the cursor that plain reads and writes use.
+    file.seek(SeekFrom::Start(5)).await?;
+    read_at(&mut file, 0, &mut buf).await?;
+    write_at(&mut file, 0, b"0").await?;
+    assert_eq!(file.stream_position().await?, 5);
+    println!("{} {:?}", "read_at/write_at ok:".green(), content);
+
+    Ok(())
+}
+
+async fn record_file_demo(dir: &TempDir) -> anyhow::Result<()> {
+    // Generate a sorted file of a million records (16 MB). Keys are
+    // multiples of 3, so the two keys between each pair are missing.
+    let path = dir.join("records.bin");
+    let mut data = Vec::with_capacity(RECORDS as usize * Record::LEN);
+    for i in 0..RECORDS {
+        data.extend_from_slice(
+            &Record {
+                key: i * 3,
+                value: i,
+            }
+            .encode(),
+        );
+    }
+    tokio::fs::write(&path, &data).await?;
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .await?;
+    let mut records = RecordFile::new(file).await?;
+    assert_eq!(records.len(), RECORDS);
+
+    // --- Direct access at the boundaries ---
+    assert_eq!(records.get(0).await?, Record { key: 0, value: 0 });
+    let last = records.get(RECORDS - 1).await?;
+    assert_eq!(last.value, RECORDS - 1);
+    assert!(records.get(RECORDS).await.is_err());
+
+    // --- Binary search at the boundaries ---
+    assert_eq!(records.binary_search(0).await?, Ok(0));
+    assert_eq!(records.binary_search(last.key).await?, Ok(RECORDS - 1));
+    assert_eq!(records.binary_search(1).await?, Err(1));
+    assert_eq!(records.binary_search(last.key + 1).await?, Err(RECORDS));
+
+    // --- Random lookups, checked against the known layout ---
+    let mut rng = rand::rng();
+    records.reads = 0;
+    let lookups = 1000;
+    for _ in 0..lookups {
+        let key = rng.random_range(0..RECORDS * 3);
+        let expected = if key % 3 == 0 {
+            Ok(key / 3)
+        } else {
+            Err(key / 3 + 1)
+        };
+        assert_eq!(records.binary_search(key).await?, expected, "key {}", key);
+    }
+    let per_lookup = records.reads as f64 / lookups as f64;
+    // log2(1_000_000) is just under 20.
+    assert!(per_lookup <= 20.0);
+    println!(
+        "{} {} lookups in {} records, {:.1} reads each",
+        "binary search ok:".green(),
+        lookups,
+        records.len(),
+        per_lookup
+    );
+
+    // --- Appending keeps the file sorted if the key is the largest ---
+    let new = Record {
+        key: last.key + 3,
+        value: RECORDS,
+    };
+    records.set(RECORDS, new).await?;
+    assert_eq!(records.binary_search(new.key).await?, Ok(RECORDS));
+    assert!(records.set(RECORDS + 5, new).await.is_err());
+
+    Ok(())
+}
+
+async fn sparse_file_demo(dir: &TempDir) -> anyhow::Result<()> {
+    // Small enough not to hurt on a file system without sparse files, where
+    // the holes really take up disk space.
+    const SIZE: u64 = 64 * 1024 * 1024;
+    let path = SparseFileBuilder::new(dir.join("sparse.bin"), SIZE)
+        .chunk(0, "header")
+        .chunk(SIZE / 2, "middle")
+        .chunk(SIZE - 6, "footer")
+        .build()
+        .await?;
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .await?;
+    let mut buf = [0xffu8; 6];
+    for (offset, expected) in
+        [(0, b"header"), (SIZE / 2, b"middle"), (SIZE - 6, b"footer")]
+    {
+        read_at(&mut file, offset, &mut buf).await?;
+        assert_eq!(&buf, expected);
+    }
+    // Holes read back as zeros.
+    read_at(&mut file, SIZE / 4, &mut buf).await?;
+    assert_eq!(buf, [0; 6]);
+
+    let meta = tokio::fs::metadata(&path).await?;
+    assert_eq!(meta.len(), SIZE);
+    // `blocks` counts 512-byte units actually allocated on disk.
+    println!(
+        "{} {} bytes long, {} bytes allocated",
+        "sparse file ok:".green(),
+        meta.len(),
+        meta.blocks() * 512
+    );
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let dir = TempDir::new("seek").await?;
+    positional_io_demo().await?;
+    record_file_demo(&dir).await?;
+    sparse_file_demo(&dir).await
+}
@@ -0,0 +1,165 @@
+use std::io::{self, SeekFrom};
+
+use tokio::io::{
+    AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt,
+};
+
+/// Reads up to `buf.len()` bytes starting at `offset`. Like `pread`, it only
+/// comes up short at the end of the file, and leaves the file's cursor where
+/// it was. Unlike `pread`, it does so with two extra seeks.
+pub async fn read_at<F>(
+    file: &mut F,
+    offset: u64,
+    buf: &mut [u8],
+) -> io::Result<usize>
+where
+    F: AsyncRead + AsyncSeek + Unpin,
+{
+    let cursor = file.stream_position().await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut filled = 0;
+    let read = async {
+        while filled < buf.len() {
+            match file.read(&mut buf[filled..]).await? {
+                0 => break, // EOF
+                n => filled += n,
+            }
+        }
+        io::Result::Ok(())
+    }
+    .await;
+    file.seek(SeekFrom::Start(cursor)).await?;
+    read.map(|()| filled)
+}
+
+/// Writes all of `data` starting at `offset`. Writing past the end extends the
+/// file; any gap in between reads back as zeros. Like `read_at`, it leaves the
+/// cursor where it was.
+pub async fn write_at<F>(
+    file: &mut F,
+    offset: u64,
+    data: &[u8],
+) -> io::Result<()>
+where
+    F: AsyncWrite + AsyncSeek + Unpin,
+{
+    let cursor = file.stream_position().await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    let written = async {
+        file.write_all(data).await?;
+        file.flush().await
+    }
+    .await;
+    file.seek(SeekFrom::Start(cursor)).await?;
+    written
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record {
+    pub key: u64,
+    pub value: u64,
+}
+
+impl Record {
+    /// Every record takes exactly this many bytes: two big-endian `u64`s.
+    pub const LEN: usize = 16;
+
+    pub fn encode(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.value.to_be_bytes());
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8; Self::LEN]) -> Self {
+        Self {
+            key: u64::from_be_bytes(bytes[..8].try_into().unwrap()),
+            value: u64::from_be_bytes(bytes[8..].try_into().unwrap()),
+        }
+    }
+}
+
+/// A file of fixed-size records, addressed by index. Record `i` lives at
+/// byte offset `i * Record::LEN`, so any record is one seek away.
+pub struct RecordFile<F> {
+    file: F,
+    len: u64,
+    /// Number of records read so far, to show how cheap a lookup is.
+    pub reads: u64,
+}
+
+impl<F> RecordFile<F>
+where
+    F: AsyncRead + AsyncWrite + AsyncSeek + Unpin,
+{
+    pub async fn new(mut file: F) -> io::Result<Self> {
+        // Seeking to the end returns the file size.
+        let size = file.seek(SeekFrom::End(0)).await?;
+        if size % Record::LEN as u64 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "file size is not a multiple of the record size",
+            ));
+        }
+        Ok(Self {
+            file,
+            len: size / Record::LEN as u64,
+            reads: 0,
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub async fn get(&mut self, index: u64) -> io::Result<Record> {
+        if index >= self.len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "record {} out of bounds ({} records)",
+                    index, self.len
+                ),
+            ));
+        }
+        let mut bytes = [0u8; Record::LEN];
+        let n = read_at(&mut self.file, index * Record::LEN as u64, &mut bytes)
+            .await?;
+        if n < Record::LEN {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.reads += 1;
+        Ok(Record::decode(&bytes))
+    }
+
+    /// Overwrites record `index`, or appends when `index == len()`.
+    pub async fn set(&mut self, index: u64, record: Record) -> io::Result<()> {
+        if index > self.len {
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+        write_at(&mut self.file, index * Record::LEN as u64, &record.encode())
+            .await?;
+        self.len = self.len.max(index + 1);
+        Ok(())
+    }
+
+    /// Binary search over a file sorted by key, with the same contract as
+    /// `slice::binary_search`: `Ok(index)` if found, otherwise `Err(index)`
+    /// where the key would have to be inserted. Reads `O(log n)` records.
+    pub async fn binary_search(
+        &mut self,
+        key: u64,
+    ) -> io::Result<Result<u64, u64>> {
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let record = self.get(mid).await?;
+            match record.key.cmp(&key) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Ok(Ok(mid)),
+            }
+        }
+        Ok(Err(low))
+    }
+}
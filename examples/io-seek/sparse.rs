@@ -0,0 +1,46 @@
+use std::{io, path::PathBuf};
+
+use tokio::fs::OpenOptions;
+
+use crate::records::write_at;
+
+/// Builds a file of a given size from a few chunks of data at arbitrary
+/// offsets. Everything in between is a *hole*: it reads as zeros, but file
+/// systems that support sparse files do not allocate disk blocks for it.
+pub struct SparseFileBuilder {
+    path: PathBuf,
+    len: u64,
+    chunks: Vec<(u64, Vec<u8>)>,
+}
+
+impl SparseFileBuilder {
+    pub fn new(path: impl Into<PathBuf>, len: u64) -> Self {
+        Self {
+            path: path.into(),
+            len,
+            chunks: Vec::new(),
+        }
+    }
+
+    pub fn chunk(mut self, offset: u64, data: impl Into<Vec<u8>>) -> Self {
+        self.chunks.push((offset, data.into()));
+        self
+    }
+
+    pub async fn build(self) -> io::Result<PathBuf> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&self.path)
+            .await?;
+        // Setting the length creates one big hole. No data is written.
+        file.set_len(self.len).await?;
+        for (offset, data) in &self.chunks {
+            write_at(&mut file, *offset, data).await?;
+        }
+        file.sync_all().await?;
+        Ok(self.path)
+    }
+}
@@ -0,0 +1,407 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use colored::Colorize;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{self, AsyncWriteExt, BufWriter},
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::{Instant, MissedTickBehavior, interval},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        };
+        f.pad(name)
+    }
+}
+
+struct Record {
+    time: SystemTime,
+    level: Level,
+    message: String,
+    fields: Vec<(String, String)>,
+}
+
+impl Record {
+    /// One line in `logfmt`: `ts=... level=info msg="..." key=value`.
+    fn format(&self) -> String {
+        let ts = self.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut line = format!(
+            "ts={}.{:03} level={} msg={:?}",
+            ts.as_secs(),
+            ts.subsec_millis(),
+            self.level,
+            self.message
+        );
+        for (key, value) in &self.fields {
+            if value.contains([' ', '"', '=']) || value.is_empty() {
+                line.push_str(&format!(" {}={:?}", key, value));
+            } else {
+                line.push_str(&format!(" {}={}", key, value));
+            }
+        }
+        line.push('\n');
+        line
+    }
+}
+
+enum Command {
+    Record(Record),
+    /// Write everything received so far to disk, then answer.
+    Flush(oneshot::Sender<()>),
+}
+
+/// Returned when logging after the writer has shut down.
+#[derive(Debug)]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "logger is shut down")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+/// The front end: cheap to clone, one per task that logs.
+#[derive(Debug, Clone)]
+pub struct Logger {
+    tx: mpsc::Sender<Command>,
+}
+
+impl Logger {
+    pub fn builder(dir: impl Into<PathBuf>) -> LoggerBuilder {
+        LoggerBuilder {
+            dir: dir.into(),
+            prefix: "app".into(),
+            capacity: 1024,
+            batch_size: 256,
+            flush_interval: Duration::from_secs(1),
+            rotate_size: 10 * 1024 * 1024,
+            rotate_every: None,
+            echo: false,
+        }
+    }
+
+    /// Waits if the channel is full, so a burst of logging slows the
+    /// producers down instead of growing memory without bound.
+    pub async fn log(
+        &self,
+        level: Level,
+        message: impl Into<String>,
+        fields: &[(&str, &(dyn fmt::Display + Sync))],
+    ) -> Result<(), Closed> {
+        let record = Record {
+            time: SystemTime::now(),
+            level,
+            message: message.into(),
+            fields: fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        };
+        self.tx
+            .send(Command::Record(record))
+            .await
+            .map_err(|_| Closed)
+    }
+
+    pub async fn info(&self, message: impl Into<String>) -> Result<(), Closed> {
+        self.log(Level::Info, message, &[]).await
+    }
+
+    /// Returns once every record logged before the call is in the file.
+    pub async fn flush(&self) -> Result<(), Closed> {
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.tx
+            .send(Command::Flush(ack_tx))
+            .await
+            .map_err(|_| Closed)?;
+        ack_rx.await.map_err(|_| Closed)
+    }
+}
+
+#[derive(Debug)]
+pub struct LoggerBuilder {
+    dir: PathBuf,
+    prefix: String,
+    capacity: usize,
+    batch_size: usize,
+    flush_interval: Duration,
+    rotate_size: u64,
+    rotate_every: Option<Duration>,
+    echo: bool,
+}
+
+impl LoggerBuilder {
+    /// Files are named `<prefix>.000.log`, `<prefix>.001.log`, ...
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// How many records may wait in the channel. At least one.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Flush after this many records...
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// ...or after this much time, whichever comes first. Must not be zero.
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Start a new file before this size would be exceeded.
+    pub fn rotate_size(mut self, bytes: u64) -> Self {
+        self.rotate_size = bytes;
+        self
+    }
+
+    /// Start a new file when the current one is this old.
+    pub fn rotate_every(mut self, age: Duration) -> Self {
+        self.rotate_every = Some(age);
+        self
+    }
+
+    /// Also print every record to stderr, in color.
+    pub fn echo(mut self, echo: bool) -> Self {
+        self.echo = echo;
+        self
+    }
+
+    /// Opens the first file and starts the background writer.
+    pub async fn start(self) -> io::Result<(Logger, WriterHandle)> {
+        // Both would make Tokio panic in the writer task instead.
+        if self.capacity == 0 {
+            return Err(invalid_input("capacity must be at least 1"));
+        }
+        if self.flush_interval.is_zero() {
+            return Err(invalid_input("flush_interval must not be zero"));
+        }
+        let (tx, rx) = mpsc::channel(self.capacity);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let mut writer = Writer::open(self).await?;
+        let task =
+            tokio::spawn(async move { writer.run(rx, shutdown_rx).await });
+        let handle = WriterHandle {
+            shutdown: shutdown_tx,
+            task,
+        };
+        Ok((Logger { tx }, handle))
+    }
+}
+
+/// What the writer did, returned on shutdown.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub records: usize,
+    pub flushes: usize,
+    pub files: Vec<PathBuf>,
+}
+
+/// Owns the background writer.
+pub struct WriterHandle {
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<io::Result<Stats>>,
+}
+
+impl WriterHandle {
+    /// Stops accepting records, writes out every record that was accepted,
+    /// and flushes. Loggers that are still around get `Closed` from now on.
+    pub async fn shutdown(self) -> io::Result<Stats> {
+        let _ = self.shutdown.send(());
+        self.task.await?
+    }
+}
+
+struct Writer {
+    config: LoggerBuilder,
+    file: BufWriter<File>,
+    /// Index of the current file.
+    index: usize,
+    file_size: u64,
+    opened_at: Instant,
+    /// Records written to the buffer but not yet flushed.
+    unflushed: usize,
+    stats: Stats,
+}
+
+impl Writer {
+    async fn open(config: LoggerBuilder) -> io::Result<Self> {
+        let (index, path, file) =
+            create_next(&config.dir, &config.prefix, 0).await?;
+        Ok(Self {
+            file: BufWriter::new(file),
+            index,
+            config,
+            file_size: 0,
+            opened_at: Instant::now(),
+            unflushed: 0,
+            stats: Stats {
+                files: vec![path],
+                ..Stats::default()
+            },
+        })
+    }
+
+    async fn run(
+        &mut self,
+        mut rx: mpsc::Receiver<Command>,
+        mut shutdown: oneshot::Receiver<()>,
+    ) -> io::Result<Stats> {
+        let mut ticker = interval(self.config.flush_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut closing = false;
+
+        loop {
+            tokio::select! {
+                command = rx.recv() => match command {
+                    Some(Command::Record(record)) => self.write(record).await?,
+                    Some(Command::Flush(ack)) => {
+                        self.flush().await?;
+                        let _ = ack.send(());
+                    }
+                    // All loggers dropped, or closed and drained.
+                    None => break,
+                },
+                _ = ticker.tick() => {
+                    if self.rotation_due(0) {
+                        self.rotate().await?;
+                    }
+                    self.flush().await?;
+                }
+                // On shutdown, close the channel instead of stopping right
+                // away: `recv` then returns what is already queued, and `None`
+                // once the queue is empty, so no accepted record is lost.
+                _ = &mut shutdown, if !closing => {
+                    closing = true;
+                    rx.close();
+                }
+            }
+        }
+
+        self.flush().await?;
+        self.file.get_ref().sync_all().await?;
+        Ok(std::mem::take(&mut self.stats))
+    }
+
+    async fn write(&mut self, record: Record) -> io::Result<()> {
+        let line = record.format();
+        if self.rotation_due(line.len() as u64) {
+            self.rotate().await?;
+        }
+        if self.config.echo {
+            let level = match record.level {
+                Level::Debug => record.level.to_string().dimmed(),
+                Level::Info => record.level.to_string().green(),
+                Level::Warn => record.level.to_string().yellow(),
+                Level::Error => record.level.to_string().red(),
+            };
+            eprintln!("{:>5} {}", level, record.message);
+        }
+
+        self.file.write_all(line.as_bytes()).await?;
+        self.file_size += line.len() as u64;
+        self.unflushed += 1;
+        self.stats.records += 1;
+        if self.unflushed >= self.config.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        if self.unflushed > 0 {
+            self.file.flush().await?;
+            self.unflushed = 0;
+            self.stats.flushes += 1;
+        }
+        Ok(())
+    }
+
+    /// Whether to start a new file before writing `next` more bytes. An
+    /// empty file is never rotated, so one huge record cannot cause a loop.
+    fn rotation_due(&self, next: u64) -> bool {
+        if self.file_size == 0 {
+            return false;
+        }
+        let too_big = self.file_size + next > self.config.rotate_size;
+        let too_old = self
+            .config
+            .rotate_every
+            .is_some_and(|age| self.opened_at.elapsed() >= age);
+        too_big || too_old
+    }
+
+    async fn rotate(&mut self) -> io::Result<()> {
+        self.flush().await?;
+        let (index, path, file) =
+            create_next(&self.config.dir, &self.config.prefix, self.index + 1)
+                .await?;
+        self.file = BufWriter::new(file);
+        self.index = index;
+        self.file_size = 0;
+        self.opened_at = Instant::now();
+        self.stats.files.push(path);
+        Ok(())
+    }
+}
+
+fn file_path(dir: &Path, prefix: &str, index: usize) -> PathBuf {
+    dir.join(format!("{}.{:03}.log", prefix, index))
+}
+
+/// Creates the first log file from index `from` on that does not exist yet.
+/// Files left by a previous run are never truncated: a restarted sink goes on
+/// after them.
+async fn create_next(
+    dir: &Path,
+    prefix: &str,
+    from: usize,
+) -> io::Result<(usize, PathBuf, File)> {
+    let mut index = from;
+    loop {
+        let path = file_path(dir, prefix, index);
+        let created = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await;
+        match created {
+            Ok(file) => return Ok((index, path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                index += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
@@ -0,0 +1,229 @@
+mod logger;
+#[path = "../common/temp.rs"]
+mod temp;
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use colored::Colorize;
+use tokio::{
+    task::{self, JoinSet},
+    time::sleep,
+};
+
+use logger::{Level, Logger, Stats};
+use temp::TempDir;
+
+const PRODUCERS: usize = 8;
+const RECORDS_EACH: usize = 2000;
+
+/// Reads all log files, in order, as one list of lines.
+async fn read_lines(stats: &Stats) -> anyhow::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for path in &stats.files {
+        let content = tokio::fs::read_to_string(path).await?;
+        lines.extend(content.lines().map(String::from));
+    }
+    Ok(lines)
+}
+
+/// The value of `key=...` in a logfmt line.
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    line.split(' ')
+        .find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+}
+
+async fn concurrent_producers(dir: &TempDir) -> anyhow::Result<()> {
+    let (logger, writer) = Logger::builder(dir.path())
+        .prefix("concurrent")
+        .capacity(64)
+        .batch_size(100)
+        .flush_interval(Duration::from_millis(50))
+        .rotate_size(64 * 1024)
+        .start()
+        .await?;
+
+    let mut producers = JoinSet::new();
+    for producer in 0..PRODUCERS {
+        let logger = logger.clone();
+        producers.spawn(async move {
+            for seq in 0..RECORDS_EACH {
+                logger
+                    .log(
+                        Level::Info,
+                        "work item done",
+                        &[("producer", &producer), ("seq", &seq)],
+                    )
+                    .await?;
+            }
+            anyhow::Ok(())
+        });
+    }
+    drop(logger);
+    while let Some(result) = producers.join_next().await {
+        result??;
+    }
+    let stats = writer.shutdown().await?;
+
+    // Every record is there exactly once, and each producer's records are
+    // in the order they were logged.
+    let lines = read_lines(&stats).await?;
+    assert_eq!(lines.len(), PRODUCERS * RECORDS_EACH);
+    let mut next_seq: HashMap<usize, usize> = HashMap::new();
+    for line in &lines {
+        let producer = field(line, "producer").unwrap().parse()?;
+        let seq: usize = field(line, "seq").unwrap().parse()?;
+        let expected = next_seq.entry(producer).or_default();
+        assert_eq!(seq, *expected, "{}", line);
+        *expected += 1;
+    }
+    assert!(next_seq.values().all(|&n| n == RECORDS_EACH));
+    for path in &stats.files {
+        assert!(tokio::fs::metadata(path).await?.len() <= 64 * 1024);
+    }
+    println!(
+        "{} {} records from {} producers in {} files, {} flushes",
+        "no records lost:".green(),
+        lines.len(),
+        PRODUCERS,
+        stats.files.len(),
+        stats.flushes
+    );
+    Ok(())
+}
+
+/// Shuts the writer down while producers are still logging.
+async fn shutdown_under_load(dir: &TempDir) -> anyhow::Result<()> {
+    let (logger, writer) = Logger::builder(dir.path())
+        .prefix("shutdown")
+        .capacity(16)
+        .start()
+        .await?;
+
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let mut producers = JoinSet::new();
+    for producer in 0..4 {
+        let logger = logger.clone();
+        let accepted = accepted.clone();
+        producers.spawn(async move {
+            // Log until the logger refuses.
+            while logger
+                .log(Level::Debug, "tick", &[("producer", &producer)])
+                .await
+                .is_ok()
+            {
+                accepted.fetch_add(1, Ordering::Relaxed);
+                task::yield_now().await;
+            }
+        });
+    }
+    while accepted.load(Ordering::Relaxed) < 1000 {
+        task::yield_now().await;
+    }
+    let stats = writer.shutdown().await?;
+    while let Some(result) = producers.join_next().await {
+        result?;
+    }
+
+    // Everything the logger accepted made it into the file.
+    let lines = read_lines(&stats).await?;
+    assert_eq!(lines.len(), accepted.load(Ordering::Relaxed));
+    assert_eq!(lines.len(), stats.records);
+    assert!(logger.info("too late").await.is_err());
+    println!(
+        "{} all {} accepted records written",
+        "shutdown under load:".green(),
+        lines.len()
+    );
+    Ok(())
+}
+
+async fn interval_flush_and_rotation(dir: &TempDir) -> anyhow::Result<()> {
+    let (logger, writer) = Logger::builder(dir.path())
+        .prefix("timed")
+        .batch_size(1000)
+        .flush_interval(Duration::from_millis(20))
+        .rotate_every(Duration::from_millis(150))
+        .echo(true)
+        .start()
+        .await?;
+    let first = dir.join("timed.000.log");
+    let second = dir.join("timed.001.log");
+
+    logger.info("service started").await?;
+    logger
+        .log(
+            Level::Warn,
+            "slow request",
+            &[("path", &"/api"), ("ms", &950)],
+        )
+        .await?;
+    // Far below the batch size, but the interval flushes it anyway.
+    sleep(Duration::from_millis(60)).await;
+    let content = tokio::fs::read_to_string(&first).await?;
+    assert!(content.contains(r#"msg="slow request" path=/api ms=950"#));
+
+    // Older than `rotate_every`: the next record goes to a new file.
+    sleep(Duration::from_millis(150)).await;
+    logger.log(Level::Error, "disk almost full", &[]).await?;
+    logger.flush().await?;
+    let content = tokio::fs::read_to_string(&second).await?;
+    assert!(content.contains("level=error"));
+
+    let stats = writer.shutdown().await?;
+    assert_eq!(stats.files, [first, second]);
+    println!("{}", "interval flush and time-based rotation ok".green());
+    Ok(())
+}
+
+/// A restarted sink goes on after the files of the previous run.
+async fn restart(dir: &TempDir) -> anyhow::Result<()> {
+    let start = || Logger::builder(dir.path()).prefix("restart").start();
+
+    let (logger, writer) = start().await?;
+    logger.info("first run").await?;
+    drop(logger);
+    writer.shutdown().await?;
+
+    let (logger, writer) = start().await?;
+    logger.info("second run").await?;
+    drop(logger);
+    let stats = writer.shutdown().await?;
+    assert_eq!(stats.files, [dir.join("restart.001.log")]);
+
+    let first = tokio::fs::read_to_string(dir.join("restart.000.log")).await?;
+    assert!(first.contains("first run") && !first.contains("second run"));
+    let second = read_lines(&stats).await?;
+    assert!(second.len() == 1 && second[0].contains("second run"));
+    println!("{}", "restart keeps the old files".green());
+    Ok(())
+}
+
+async fn invalid_config(dir: &TempDir) {
+    let zero_capacity = Logger::builder(dir.path()).capacity(0).start().await;
+    assert!(zero_capacity.is_err());
+    let zero_interval = Logger::builder(dir.path())
+        .flush_interval(Duration::ZERO)
+        .start()
+        .await;
+    assert!(zero_interval.is_err());
+}
+
+// The clock is paused, so the flush and rotation timings are exact. File I/O
+// runs on blocking threads, and Tokio does not skip time while it does.
+#[tokio::main(flavor = "current_thread", start_paused = true)]
+async fn main() -> anyhow::Result<()> {
+    let dir = TempDir::new("logs").await?;
+    concurrent_producers(&dir).await?;
+    shutdown_under_load(&dir).await?;
+    interval_flush_and_rotation(&dir).await?;
+    restart(&dir).await?;
+    invalid_config(&dir).await;
+    Ok(())
+}
@@ -0,0 +1,95 @@
+#[path = "../common/dashboard.rs"]
+mod dashboard;
+
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::time::sleep;
+
+use dashboard::{Dashboard, Metrics};
+
+fn fib(v: usize) -> usize {
+    match v {
+        0 => 0,
+        1 => 1,
+        _ => fib(v - 1) + fib(v - 2),
+    }
+}
+
+/// Cheap enough to check results with, on the async task.
+fn fib_iterative(v: usize) -> usize {
+    (0..v).fold((0, 1), |(a, b), _| (b, a + b)).0
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // cargo run --example metrics-dashboard -- 38
+    let max: usize = std::env::args().nth(1).map_or(Ok(32), |n| n.parse())?;
+
+    let metrics = Metrics::new();
+    let dashboard = Dashboard::new(metrics.clone());
+    let view = dashboard.start(Duration::from_millis(250));
+
+    // The workload of `task-management-joinset-blocking.rs`.
+    let mut fibs = Vec::new();
+    for value in 0..=max {
+        fibs.push(metrics.spawn_blocking("fib", move || fib(value)));
+    }
+
+    // Tasks that mostly wait, as I/O-bound tasks do: many short polls.
+    let mut waiters = Vec::new();
+    for _ in 0..50 {
+        waiters.push(metrics.spawn("waiter", async {
+            for _ in 0..10 {
+                let delay = rand::rng().random_range(10..100);
+                sleep(Duration::from_millis(delay)).await;
+            }
+        }));
+    }
+
+    // A task that forgot `spawn_blocking`: few polls, each of them long.
+    let inline_values = max.saturating_sub(5)..max;
+    let inline_polls = inline_values.len() as u64 + 1;
+    let inline = metrics.spawn("fib-inline", async move {
+        let mut total = 0;
+        for value in inline_values {
+            total += fib(value);
+            tokio::task::yield_now().await;
+        }
+        total
+    });
+
+    for (value, handle) in fibs.into_iter().enumerate() {
+        let result = handle.await?;
+        assert_eq!(result, fib_iterative(value));
+    }
+    for handle in waiters {
+        handle.await?;
+    }
+    inline.await?;
+    view.stop().await;
+
+    let stats = metrics.snapshot();
+    let names: Vec<&str> = stats.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["fib", "fib-inline", "waiter"]);
+    for task in &stats {
+        assert_eq!(task.running(), 0, "{:?}", task);
+    }
+    let [fib_stats, inline, waiter] = &stats[..] else {
+        unreachable!()
+    };
+    assert!(fib_stats.blocking);
+    assert_eq!(fib_stats.completed as usize, max + 1);
+    assert_eq!(fib_stats.polls, fib_stats.completed);
+    assert_eq!(waiter.completed, 50);
+    // Each waiter: one poll to start, one per sleep.
+    assert!(waiter.polls >= 50 * 11, "{:?}", waiter);
+    // The inline task spent far longer per poll than the waiters, unless
+    // its values are too small to take any time.
+    assert_eq!(inline.polls, inline_polls);
+    if max >= 25 {
+        let per_poll = inline.busy / inline_polls as u32;
+        assert!(per_poll > waiter.busy / waiter.polls as u32 * 100);
+    }
+    Ok(())
+}
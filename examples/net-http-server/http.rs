@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, bail, ensure};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+};
+
+/// Longest request line or header line we are willing to buffer.
+const MAX_LINE: u64 = 8 * 1024;
+/// Maximum number of headers in a single request.
+const MAX_HEADERS: usize = 64;
+/// Maximum size of a request body, whether sent with `Content-Length` or chunked.
+const MAX_BODY: usize = 1024 * 1024;
+
+/// The request body is over `MAX_BODY`; answered with 413 rather than 400.
+#[derive(Debug)]
+pub struct BodyTooLarge;
+
+impl std::fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "body larger than {} bytes", MAX_BODY)
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    Http10,
+    Http11,
+}
+
+/// Header names are stored as received; lookups are case-insensitive.
+#[derive(Debug, Default)]
+pub struct Headers(Vec<(String, String)>);
+
+impl Headers {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn insert(&mut self, name: &str, value: impl Into<String>) {
+        self.0.push((name.to_string(), value.into()));
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &(String, String)> {
+        self.0.iter()
+    }
+}
+
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub version: Version,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+    /// Filled in by the router with the values captured by `:name` and `*name`
+    /// segments of the matched route.
+    pub params: HashMap<String, String>,
+}
+
+impl Request {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+
+    /// HTTP/1.1 connections are persistent unless the client says otherwise,
+    /// HTTP/1.0 connections are closed unless the client asks to keep them.
+    pub fn keep_alive(&self) -> bool {
+        let connection = self.headers.get("Connection").unwrap_or_default();
+        match self.version {
+            Version::Http11 => !connection.eq_ignore_ascii_case("close"),
+            Version::Http10 => connection.eq_ignore_ascii_case("keep-alive"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(
+        status: u16,
+        content_type: &str,
+        body: impl Into<Vec<u8>>,
+    ) -> Self {
+        let mut headers = Headers::default();
+        headers.insert("Content-Type", content_type);
+        Self {
+            status,
+            headers,
+            body: body.into(),
+        }
+    }
+
+    pub fn text(status: u16, body: impl Into<String>) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", body.into())
+    }
+
+    /// Writes status line, headers and body. `Content-Length` and `Connection`
+    /// are always set by us so the client knows where the response ends.
+    pub async fn write_to<W>(
+        &self,
+        writer: &mut W,
+        keep_alive: bool,
+    ) -> anyhow::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut head =
+            format!("HTTP/1.1 {} {}\r\n", self.status, reason(self.status));
+        for (name, value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        let connection = if keep_alive { "keep-alive" } else { "close" };
+        head.push_str(&format!("Connection: {}\r\n\r\n", connection));
+
+        writer.write_all(head.as_bytes()).await?;
+        writer.write_all(&self.body).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+/// Reads one CRLF (or bare LF) terminated line without the line ending.
+/// Returns `None` on a clean EOF before any byte was read.
+async fn read_line<R>(reader: &mut R) -> anyhow::Result<Option<String>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = String::new();
+    // `take` bounds how much a misbehaving client can make us buffer.
+    let n = (&mut *reader).take(MAX_LINE).read_line(&mut line).await?;
+    if n == 0 {
+        return Ok(None);
+    }
+    ensure!(line.ends_with('\n'), "line too long or truncated");
+    line.truncate(line.trim_end_matches(['\r', '\n']).len());
+    Ok(Some(line))
+}
+
+/// Reads header lines up to and including the empty line that ends them.
+pub async fn read_headers<R>(reader: &mut R) -> anyhow::Result<Headers>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut headers = Headers::default();
+    loop {
+        let line = read_line(reader)
+            .await?
+            .context("connection closed inside headers")?;
+        if line.is_empty() {
+            return Ok(headers);
+        }
+        ensure!(headers.0.len() < MAX_HEADERS, "too many headers");
+        let (name, value) = line.split_once(':').context("malformed header")?;
+        ensure!(
+            !name.is_empty() && !name.contains(' '),
+            "malformed header name"
+        );
+        headers.insert(name, value.trim());
+    }
+}
+
+/// Reads the next request from a connection. `Ok(None)` means the client
+/// closed the connection between requests, which is how keep-alive ends.
+pub async fn read_request<R>(reader: &mut R) -> anyhow::Result<Option<Request>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let Some(request_line) = read_line(reader).await? else {
+        return Ok(None);
+    };
+
+    // e.g. "GET /users/42?verbose=1 HTTP/1.1"
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        bail!("malformed request line: {:?}", request_line);
+    };
+    let version = match version {
+        "HTTP/1.1" => Version::Http11,
+        "HTTP/1.0" => Version::Http10,
+        other => bail!("unsupported version: {}", other),
+    };
+    ensure!(target.starts_with('/'), "request target must be a path");
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (target, None),
+    };
+
+    let headers = read_headers(reader).await?;
+    let body = read_body(reader, &headers).await?;
+
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        version,
+        headers,
+        body,
+        params: HashMap::new(),
+    }))
+}
+
+async fn read_body<R>(
+    reader: &mut R,
+    headers: &Headers,
+) -> anyhow::Result<Vec<u8>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let chunked = headers
+        .get("Transfer-Encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"));
+    if chunked {
+        return read_chunked_body(reader).await;
+    }
+
+    let Some(length) = headers.get("Content-Length") else {
+        return Ok(Vec::new());
+    };
+    let length: usize = length.parse().context("invalid Content-Length")?;
+    if length > MAX_BODY {
+        bail!(BodyTooLarge);
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(body)
+}
+
+/// Chunked bodies are a series of `<hex size>\r\n<data>\r\n` blocks terminated
+/// by a zero sized chunk and an (optionally empty) trailer section.
+async fn read_chunked_body<R>(reader: &mut R) -> anyhow::Result<Vec<u8>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut body = Vec::new();
+    loop {
+        let line = read_line(reader).await?.context("eof in chunk size")?;
+        // Chunk extensions (";name=value") are allowed and ignored.
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size =
+            usize::from_str_radix(size, 16).context("invalid chunk size")?;
+        if size == 0 {
+            // Trailers use the same syntax as headers; we read and discard them.
+            read_headers(reader).await?;
+            return Ok(body);
+        }
+        // `size` comes from the client: written as a subtraction, a huge
+        // value cannot overflow.
+        if size > MAX_BODY - body.len() {
+            bail!(BodyTooLarge);
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..]).await?;
+
+        let mut crlf = [0u8; 2];
+        reader.read_exact(&mut crlf).await?;
+        ensure!(&crlf == b"\r\n", "chunk not terminated by CRLF");
+    }
+}
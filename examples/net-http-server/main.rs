@@ -0,0 +1,196 @@
+mod http;
+mod router;
+mod server;
+mod static_files;
+#[path = "../common/temp.rs"]
+mod temp;
+
+use std::{path::PathBuf, sync::Arc};
+
+use colored::Colorize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+use http::Response;
+use router::Router;
+use temp::TempDir;
+
+/// A parsed response as seen by our raw client.
+struct ClientResponse {
+    status: u16,
+    headers: http::Headers,
+    body: String,
+}
+
+async fn read_response(
+    reader: &mut BufReader<TcpStream>,
+) -> anyhow::Result<ClientResponse> {
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).await?;
+    // "HTTP/1.1 200 OK" -> 200
+    let status = status_line
+        .split(' ')
+        .nth(1)
+        .ok_or_else(|| anyhow::anyhow!("bad status line {:?}", status_line))?
+        .parse()?;
+
+    let headers = http::read_headers(reader).await?;
+    let length: usize = headers.get("Content-Length").unwrap_or("0").parse()?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    Ok(ClientResponse {
+        status,
+        headers,
+        body: String::from_utf8(body)?,
+    })
+}
+
+/// Opens a fresh connection, sends `raw` verbatim and reads one response.
+async fn send(addr: &str, raw: &str) -> anyhow::Result<ClientResponse> {
+    let mut stream = BufReader::new(TcpStream::connect(addr).await?);
+    stream.get_mut().write_all(raw.as_bytes()).await?;
+    read_response(&mut stream).await
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // A directory of static files for the `/static/*path` route.
+    let root = TempDir::new("http-static").await?;
+    tokio::fs::write(root.join("index.html"), "<h1>Hello from disk</h1>")
+        .await?;
+    let static_root = Arc::new(root.path().to_path_buf());
+
+    let router = Router::new()
+        .get("/hello", |req| async move {
+            // "/hello?name=Ferris" -> "Hello, Ferris!"
+            let name =
+                req.query.as_deref().and_then(|q| q.strip_prefix("name="));
+            Response::text(200, format!("Hello, {}!", name.unwrap_or("world")))
+        })
+        .get("/users/:id", |req| async move {
+            Response::text(
+                200,
+                format!("user {}", req.param("id").unwrap_or("?")),
+            )
+        })
+        .post("/echo", |req| async move {
+            Response::new(200, "application/octet-stream", req.body)
+        })
+        .get("/static/*path", move |req| {
+            let root: Arc<PathBuf> = Arc::clone(&static_root);
+            async move {
+                static_files::serve(&root, req.param("path").unwrap_or(""))
+                    .await
+            }
+        });
+
+    // Port 0 asks the OS for any free port.
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?.to_string();
+    tokio::spawn(server::serve(listener, router));
+    println!("Listening on {}", addr.color("cyan"));
+
+    // --- Simple GET ---
+    let res = send(&addr, "GET /hello HTTP/1.1\r\nHost: x\r\n\r\n").await?;
+    assert_eq!((res.status, res.body.as_str()), (200, "Hello, world!"));
+    println!("GET /hello -> {}", res.body.color("green"));
+    let res = send(&addr, "GET /hello?name=Ferris HTTP/1.1\r\n\r\n").await?;
+    assert_eq!(res.body, "Hello, Ferris!");
+
+    // --- Keep-alive: two requests on the same connection ---
+    let mut conn = BufReader::new(TcpStream::connect(&addr).await?);
+    conn.get_mut()
+        .write_all(
+            b"GET /hello HTTP/1.1\r\n\r\nGET /users/42?x=1 HTTP/1.1\r\n\r\n",
+        )
+        .await?;
+    let first = read_response(&mut conn).await?;
+    let second = read_response(&mut conn).await?;
+    assert_eq!(first.headers.get("Connection"), Some("keep-alive"));
+    assert_eq!((second.status, second.body.as_str()), (200, "user 42"));
+    println!(
+        "keep-alive -> {} then {}",
+        first.body.green(),
+        second.body.green()
+    );
+
+    // --- Content-Length body ---
+    let res = send(
+        &addr,
+        "POST /echo HTTP/1.1\r\nContent-Length: 11\r\n\r\nhello there",
+    )
+    .await?;
+    assert_eq!(res.body, "hello there");
+    println!("POST /echo (Content-Length) -> {}", res.body.green());
+
+    // --- Chunked body ---
+    let res = send(
+        &addr,
+        "POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+         4\r\nWiki\r\n5;ext=1\r\npedia\r\n0\r\nX-Trailer: yes\r\n\r\n",
+    )
+    .await?;
+    assert_eq!(res.body, "Wikipedia");
+    println!("POST /echo (chunked) -> {}", res.body.green());
+
+    // --- Oversized bodies ---
+    let res = send(
+        &addr,
+        "POST /echo HTTP/1.1\r\nContent-Length: 99999999\r\n\r\n",
+    )
+    .await?;
+    assert_eq!(res.status, 413);
+    // A chunk size that would overflow `usize` when added to the body.
+    let res = send(
+        &addr,
+        "POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+         4\r\nWiki\r\nffffffffffffffff\r\n",
+    )
+    .await?;
+    assert_eq!(res.status, 413);
+    println!("oversized bodies -> {}", "413".green());
+
+    // --- Static files ---
+    let res = send(&addr, "GET /static/index.html HTTP/1.1\r\n\r\n").await?;
+    assert_eq!(res.status, 200);
+    assert_eq!(
+        res.headers.get("content-type"),
+        Some("text/html; charset=utf-8")
+    );
+    assert_eq!(res.body, "<h1>Hello from disk</h1>");
+    let res = send(&addr, "GET /static/ HTTP/1.1\r\n\r\n").await?;
+    assert_eq!(res.body, "<h1>Hello from disk</h1>");
+    let res = send(&addr, "GET /static/../etc/passwd HTTP/1.1\r\n\r\n").await?;
+    assert_eq!(res.status, 403);
+    let res = send(&addr, "GET /static/missing.txt HTTP/1.1\r\n\r\n").await?;
+    assert_eq!(res.status, 404);
+    println!("static files -> {}", "ok".green());
+
+    // --- Routing errors ---
+    assert_eq!(send(&addr, "GET /nope HTTP/1.1\r\n\r\n").await?.status, 404);
+    assert_eq!(
+        send(&addr, "DELETE /hello HTTP/1.1\r\n\r\n").await?.status,
+        405
+    );
+    assert_eq!(send(&addr, "NONSENSE\r\n\r\n").await?.status, 400);
+    println!("404 / 405 / 400 -> {}", "ok".green());
+
+    // --- Connection: close ends the connection after the response ---
+    let mut conn = BufReader::new(TcpStream::connect(&addr).await?);
+    conn.get_mut()
+        .write_all(b"GET /hello HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .await?;
+    let res = read_response(&mut conn).await?;
+    assert_eq!(res.headers.get("Connection"), Some("close"));
+    let mut rest = Vec::new();
+    assert_eq!(conn.read_to_end(&mut rest).await?, 0); // server hung up
+    println!("Connection: close -> {}", "ok".green());
+
+    // Clean up
+    root.close().await?;
+
+    Ok(())
+}
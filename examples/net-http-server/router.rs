@@ -0,0 +1,129 @@
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
+
+use crate::http::{Request, Response};
+
+type BoxFuture = Pin<Box<dyn Future<Output = Response> + Send>>;
+type Handler = Arc<dyn Fn(Request) -> BoxFuture + Send + Sync>;
+
+enum Segment {
+    /// `/users` matches only `users`.
+    Literal(String),
+    /// `/:id` matches any single segment and captures it as `id`.
+    Param(String),
+    /// `/*path` matches the rest of the path (possibly empty) as `path`.
+    Rest(String),
+}
+
+struct Route {
+    method: String,
+    segments: Vec<Segment>,
+    handler: Handler,
+}
+
+impl Route {
+    fn matches(&self, path: &[&str]) -> Option<HashMap<String, String>> {
+        let mut params = HashMap::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Rest(name) => {
+                    params.insert(name.clone(), path.get(i..)?.join("/"));
+                    return Some(params);
+                }
+                Segment::Literal(literal)
+                    if path.get(i) == Some(&literal.as_str()) => {}
+                Segment::Param(name) => {
+                    params.insert(name.clone(), path.get(i)?.to_string());
+                }
+                Segment::Literal(_) => return None,
+            }
+        }
+        (path.len() == self.segments.len()).then_some(params)
+    }
+}
+
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route<F, Fut>(
+        mut self,
+        method: &str,
+        pattern: &str,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        let segments = split_path(pattern)
+            .into_iter()
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix(':') {
+                    Segment::Param(name.to_string())
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    Segment::Rest(name.to_string())
+                } else {
+                    Segment::Literal(segment.to_string())
+                }
+            })
+            .collect();
+
+        self.routes.push(Route {
+            method: method.to_string(),
+            segments,
+            handler: Arc::new(move |request| Box::pin(handler(request))),
+        });
+        self
+    }
+
+    pub fn get<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        self.route("GET", pattern, handler)
+    }
+
+    pub fn post<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        self.route("POST", pattern, handler)
+    }
+
+    /// Routes are tried in the order they were added. A path that matches some
+    /// route but not with this method is a `405`, anything else a `404`.
+    pub async fn handle(&self, mut request: Request) -> Response {
+        let path = split_path(&request.path);
+        let mut path_matched = false;
+
+        for route in &self.routes {
+            let Some(params) = route.matches(&path) else {
+                continue;
+            };
+            if route.method != request.method {
+                path_matched = true;
+                continue;
+            }
+            request.params = params;
+            return (route.handler)(request).await;
+        }
+
+        if path_matched {
+            Response::text(405, "method not allowed\n")
+        } else {
+            Response::text(404, "not found\n")
+        }
+    }
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.split('/').filter(|s| !s.is_empty()).collect()
+}
@@ -0,0 +1,65 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{
+    io::BufReader,
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+
+use crate::{http, http::Response, router::Router};
+
+/// How long an idle keep-alive connection may wait for its next request.
+const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Accepts connections forever, handling each one on its own task.
+pub async fn serve(
+    listener: TcpListener,
+    router: Router,
+) -> anyhow::Result<()> {
+    let router = Arc::new(router);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let router = Arc::clone(&router);
+        tokio::spawn(async move {
+            if let Err(err) = handle_connection(stream, router).await {
+                eprintln!("connection {} failed: {:#}", peer, err);
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    router: Arc<Router>,
+) -> anyhow::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        let request =
+            match timeout(KEEP_ALIVE_TIMEOUT, http::read_request(&mut reader))
+                .await
+            {
+                Ok(Ok(Some(request))) => request,
+                // Client closed the connection, or stayed idle for too long.
+                Ok(Ok(None)) | Err(_) => return Ok(()),
+                Ok(Err(err)) => {
+                    let response = if err.is::<http::BodyTooLarge>() {
+                        Response::text(413, format!("{}\n", err))
+                    } else {
+                        Response::text(400, format!("bad request: {}\n", err))
+                    };
+                    response.write_to(&mut writer, false).await?;
+                    return Err(err);
+                }
+            };
+
+        let keep_alive = request.keep_alive();
+        let response = router.handle(request).await;
+        response.write_to(&mut writer, keep_alive).await?;
+
+        if !keep_alive {
+            return Ok(());
+        }
+    }
+}
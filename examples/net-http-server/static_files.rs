@@ -0,0 +1,43 @@
+use std::{
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::http::Response;
+
+/// Serves `relative` from inside `root`. Anything that is not a plain file name
+/// (`..`, absolute paths, prefixes) is refused so a request cannot escape `root`.
+pub async fn serve(root: &Path, relative: &str) -> Response {
+    let relative = Path::new(relative);
+    if !relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Response::text(403, "forbidden\n");
+    }
+
+    let mut path: PathBuf = root.join(relative);
+    if tokio::fs::metadata(&path).await.is_ok_and(|m| m.is_dir()) {
+        path.push("index.html");
+    }
+
+    match tokio::fs::read(&path).await {
+        Ok(contents) => Response::new(200, content_type(&path), contents),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            Response::text(404, "not found\n")
+        }
+        Err(err) => Response::text(500, format!("{}\n", err)),
+    }
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    }
+}
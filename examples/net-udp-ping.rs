@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use colored::Colorize;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use tokio::{
+    net::UdpSocket,
+    time::{Instant, timeout},
+};
+
+/// Every datagram carries a sequence number and the attempt number of the
+/// transmission, so a reply can be matched with the exact send it answers.
+const PACKET_LEN: usize = 5;
+
+fn encode(seq: u32, attempt: u8) -> [u8; PACKET_LEN] {
+    let mut packet = [0u8; PACKET_LEN];
+    packet[..4].copy_from_slice(&seq.to_be_bytes());
+    packet[4] = attempt;
+    packet
+}
+
+fn decode(packet: &[u8]) -> Option<(u32, u8)> {
+    let packet: &[u8; PACKET_LEN] = packet.try_into().ok()?;
+    let seq = u32::from_be_bytes(packet[..4].try_into().ok()?);
+    Some((seq, packet[4]))
+}
+
+/// Echoes every datagram back to its sender, except the ones it randomly
+/// "loses" to simulate an unreliable network.
+async fn pong_server(
+    socket: UdpSocket,
+    drop_rate: f64,
+    seed: u64,
+) -> anyhow::Result<()> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut buf = [0u8; 64];
+    loop {
+        let (n, peer) = socket.recv_from(&mut buf).await?;
+        if rng.random_bool(drop_rate) {
+            continue;
+        }
+        socket.send_to(&buf[..n], peer).await?;
+    }
+}
+
+struct PingConfig {
+    count: u32,
+    /// How long to wait for a reply before retransmitting.
+    retransmit_after: Duration,
+    /// Total number of transmissions per sequence number before giving up.
+    max_attempts: u8,
+}
+
+#[derive(Default)]
+struct PingReport {
+    received: u32,
+    lost: u32,
+    retransmissions: u32,
+    rtts: Vec<Duration>,
+}
+
+impl PingReport {
+    fn print(&self, count: u32) {
+        let loss = 100.0 * self.lost as f64 / count as f64;
+        println!(
+            "  {} sent, {} received, {} retransmissions, {:.1}% loss",
+            count, self.received, self.retransmissions, loss
+        );
+        if let (Some(min), Some(max)) =
+            (self.rtts.iter().min(), self.rtts.iter().max())
+        {
+            let avg =
+                self.rtts.iter().sum::<Duration>() / self.rtts.len() as u32;
+            println!("  rtt min/avg/max = {:?}/{:?}/{:?}", min, avg, max);
+        }
+    }
+}
+
+async fn ping(
+    socket: &UdpSocket,
+    config: &PingConfig,
+) -> anyhow::Result<PingReport> {
+    let mut report = PingReport::default();
+    let mut buf = [0u8; 64];
+
+    for seq in 0..config.count {
+        let mut answered = false;
+
+        for attempt in 0..config.max_attempts {
+            if attempt > 0 {
+                report.retransmissions += 1;
+            }
+            let sent_at = Instant::now();
+            socket.send(&encode(seq, attempt)).await?;
+
+            // Keep reading until the reply for *this* transmission arrives or
+            // the retransmission timer fires. Late replies to earlier sends
+            // (or earlier sequence numbers) are simply skipped.
+            let reply = timeout(config.retransmit_after, async {
+                loop {
+                    let n = socket.recv(&mut buf).await?;
+                    if decode(&buf[..n]) == Some((seq, attempt)) {
+                        return anyhow::Ok(Instant::now());
+                    }
+                }
+            })
+            .await;
+
+            // Only the timeout means "try again". A failed `recv` (say, an
+            // ICMP port unreachable reported as `ConnectionRefused`) is a
+            // real error, not a lost packet.
+            match reply {
+                Ok(Ok(received_at)) => {
+                    report.rtts.push(received_at - sent_at);
+                    answered = true;
+                    break;
+                }
+                Ok(Err(err)) => return Err(err),
+                Err(_elapsed) => {}
+            }
+        }
+
+        if answered {
+            report.received += 1;
+        } else {
+            report.lost += 1;
+            println!("  seq={} {}", seq, "lost".red());
+        }
+    }
+
+    Ok(report)
+}
+
+async fn run(
+    drop_rate: f64,
+    config: &PingConfig,
+) -> anyhow::Result<PingReport> {
+    let server = UdpSocket::bind("127.0.0.1:0").await?;
+    let server_addr = server.local_addr()?;
+    let server = tokio::spawn(pong_server(server, drop_rate, 42));
+
+    // `connect` fixes the peer address, so we can use `send`/`recv` and the
+    // socket ignores datagrams from anyone else.
+    let client = UdpSocket::bind("127.0.0.1:0").await?;
+    client.connect(server_addr).await?;
+
+    println!(
+        "Pinging {} with drop rate {}:",
+        server_addr,
+        format!("{:.0}%", drop_rate * 100.0).yellow()
+    );
+    let report = ping(&client, config).await?;
+    report.print(config.count);
+
+    server.abort();
+    Ok(report)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let config = PingConfig {
+        count: 20,
+        retransmit_after: Duration::from_millis(50),
+        max_attempts: 6,
+    };
+
+    // A perfect network: no retransmissions, no loss.
+    let report = run(0.0, &config).await?;
+    assert_eq!(
+        (report.received, report.lost, report.retransmissions),
+        (20, 0, 0)
+    );
+
+    // A lossy network: retransmissions hide most (usually all) of the loss.
+    let report = run(0.3, &config).await?;
+    assert_eq!(report.received + report.lost, config.count);
+    assert!(report.retransmissions > 0);
+    assert_eq!(report.rtts.len() as u32, report.received);
+
+    // A dead network: every ping is eventually given up on.
+    let config = PingConfig {
+        count: 3,
+        retransmit_after: Duration::from_millis(20),
+        max_attempts: 3,
+    };
+    let report = run(1.0, &config).await?;
+    assert_eq!(
+        (report.received, report.lost, report.retransmissions),
+        (0, 3, 6)
+    );
+
+    // Nobody listening: the ICMP "port unreachable" surfaces as an error from
+    // `recv` and ends the ping instead of being counted as loss.
+    let closed = UdpSocket::bind("127.0.0.1:0").await?;
+    let closed_addr = closed.local_addr()?;
+    drop(closed);
+    let client = UdpSocket::bind("127.0.0.1:0").await?;
+    client.connect(closed_addr).await?;
+    let Err(err) = ping(&client, &config).await else {
+        panic!("pinging a closed port succeeded");
+    };
+    let err = err.downcast_ref::<std::io::Error>().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+    println!("{} {}", "Pinging a closed port:".yellow(), err);
+
+    Ok(())
+}
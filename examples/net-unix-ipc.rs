@@ -0,0 +1,251 @@
+use std::{
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use anyhow::ensure;
+use colored::Colorize;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{UnixListener, UnixStream},
+    process::Command,
+    sync::oneshot,
+    task::{JoinError, JoinSet},
+    time::{Instant, timeout_at},
+};
+
+/// Upper bound for a single message, so a bogus length prefix cannot make us
+/// allocate gigabytes.
+const MAX_MESSAGE: u32 = 64 * 1024;
+/// How long a shutdown waits for open connections before cutting them off.
+const SHUTDOWN_GRACE: Duration = Duration::from_millis(500);
+
+/// Writes `payload` prefixed by its length as a big-endian `u32`.
+async fn write_message<W>(writer: &mut W, payload: &[u8]) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    ensure!(
+        payload.len() as u64 <= MAX_MESSAGE as u64,
+        "message too large"
+    );
+    writer.write_u32(payload.len() as u32).await?;
+    writer.write_all(payload).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Reads one length-prefixed message. `Ok(None)` means the peer closed the
+/// connection cleanly between two messages.
+async fn read_message<R>(reader: &mut R) -> anyhow::Result<Option<Vec<u8>>>
+where
+    R: AsyncRead + Unpin,
+{
+    let len = match reader.read_u32().await {
+        Ok(len) => len,
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+            return Ok(None);
+        }
+        Err(err) => return Err(err.into()),
+    };
+    ensure!(len <= MAX_MESSAGE, "message of {} bytes exceeds limit", len);
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload).await?;
+    Ok(Some(payload))
+}
+
+/// Removes the socket file when dropped, so the next run can bind again even
+/// if the server exits with an error.
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn handle_request(request: &str, peer_pid: Option<i32>) -> String {
+    match request.split_once(' ') {
+        Some(("upper", text)) => text.to_uppercase(),
+        Some(("add", numbers)) => numbers
+            .split_whitespace()
+            .map(str::parse::<i64>)
+            .sum::<Result<i64, _>>()
+            .map_or_else(
+                |err| format!("error: {}", err),
+                |sum| sum.to_string(),
+            ),
+        _ if request == "ping" => "pong".to_string(),
+        _ if request == "whoami" => format!("pid {}", peer_pid.unwrap_or(-1)),
+        _ => format!("error: unknown request {:?}", request),
+    }
+}
+
+async fn handle_connection(
+    mut stream: UnixStream,
+    owner_uid: u32,
+) -> anyhow::Result<u32> {
+    // The kernel tells us who is on the other end of a Unix socket. This is
+    // how local daemons authorise clients without passwords.
+    let cred = stream.peer_cred()?;
+    println!(
+        "[server] client connected: uid={} gid={} pid={:?}",
+        cred.uid(),
+        cred.gid(),
+        cred.pid()
+    );
+    ensure!(cred.uid() == owner_uid, "refusing client of another user");
+
+    let mut handled = 0;
+    while let Some(request) = read_message(&mut stream).await? {
+        let request = String::from_utf8(request)?;
+        let response = handle_request(&request, cred.pid());
+        println!("[server] {} -> {}", request.cyan(), response.green());
+        write_message(&mut stream, response.as_bytes()).await?;
+        handled += 1;
+    }
+    Ok(handled)
+}
+
+fn bind(path: &Path) -> anyhow::Result<(UnixListener, SocketFile)> {
+    // A socket file left behind by a crashed run would make `bind` fail with
+    // `AddrInUse`.
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+    Ok((listener, SocketFile(path.to_path_buf())))
+}
+
+/// The number of requests a finished connection handled. A connection that
+/// failed is logged; it only ends that one client's session.
+fn finished(result: Result<anyhow::Result<u32>, JoinError>) -> u32 {
+    match result {
+        Ok(Ok(handled)) => handled,
+        Ok(Err(err)) => {
+            println!("[server] {} {:#}", "connection failed:".red(), err);
+            0
+        }
+        Err(err) => {
+            println!("[server] {} {}", "connection task failed:".red(), err);
+            0
+        }
+    }
+}
+
+/// Serves each connection in its own task until `shutdown` fires. Returns
+/// the total number of requests handled. The socket file is removed on
+/// return.
+async fn server(
+    listener: UnixListener,
+    socket_file: SocketFile,
+    mut shutdown: oneshot::Receiver<()>,
+) -> anyhow::Result<u32> {
+    // Only the user who owns the socket file may talk to us.
+    let owner_uid = std::fs::metadata(&socket_file.0)?.uid();
+
+    let mut connections = JoinSet::new();
+    let mut handled = 0;
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _addr) = accepted?;
+                connections.spawn(handle_connection(stream, owner_uid));
+            }
+            Some(result) = connections.join_next() => {
+                handled += finished(result);
+            }
+            _ = &mut shutdown => break,
+        }
+    }
+
+    // Let the open connections finish, but do not wait forever for a client
+    // that never sends anything.
+    println!("[server] shutting down");
+    let deadline = Instant::now() + SHUTDOWN_GRACE;
+    loop {
+        match timeout_at(deadline, connections.join_next()).await {
+            Ok(Some(result)) => handled += finished(result),
+            Ok(None) => break,
+            Err(_elapsed) => {
+                println!(
+                    "[server] closing {} idle connection(s)",
+                    connections.len()
+                );
+                connections.shutdown().await;
+                break;
+            }
+        }
+    }
+    Ok(handled)
+}
+
+/// Runs in the child process: sends a few requests and checks the answers.
+async fn client(path: &Path) -> anyhow::Result<()> {
+    let mut stream = UnixStream::connect(path).await?;
+    let my_pid = std::process::id();
+
+    let exchanges = [
+        ("ping".to_string(), "pong".to_string()),
+        ("upper hello ipc".to_string(), "HELLO IPC".to_string()),
+        ("add 1 2 3 4".to_string(), "10".to_string()),
+        ("whoami".to_string(), format!("pid {}", my_pid)),
+    ];
+    for (request, expected) in exchanges {
+        write_message(&mut stream, request.as_bytes()).await?;
+        let response = read_message(&mut stream).await?;
+        let response = String::from_utf8(response.unwrap_or_default())?;
+        ensure!(response == expected, "{:?}: got {:?}", request, response);
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // The same binary is the client when started with `client <socket path>`.
+    let args: Vec<String> = std::env::args().collect();
+    if let [_, mode, path] = args.as_slice()
+        && mode == "client"
+    {
+        return client(Path::new(path)).await;
+    }
+
+    let path =
+        std::env::temp_dir().join(format!("ipc-{}.sock", std::process::id()));
+    let (listener, socket_file) = bind(&path)?;
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server = tokio::spawn(server(listener, socket_file, shutdown_rx));
+
+    // Misbehaving clients: each one only ends its own connection. One that
+    // connects and then says nothing holds up neither the others nor the
+    // shutdown.
+    let stalled = UnixStream::connect(&path).await?;
+    let mut oversized = UnixStream::connect(&path).await?;
+    oversized.write_u32(MAX_MESSAGE + 1).await?;
+    let mut garbage = UnixStream::connect(&path).await?;
+    write_message(&mut garbage, &[0xff, 0xfe]).await?;
+    // The server closes both connections after logging the error.
+    assert!(read_message(&mut oversized).await?.is_none());
+    assert!(read_message(&mut garbage).await?.is_none());
+
+    let mut child = Command::new(std::env::current_exe()?)
+        .arg("client")
+        .arg(&path)
+        .stdout(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()?;
+    println!("[parent] spawned client with pid {:?}", child.id());
+
+    let status = child.wait().await?;
+    println!("[parent] client exited with {}", status);
+    assert!(status.success());
+
+    shutdown_tx.send(()).ok();
+    let handled = server.await??;
+    assert_eq!(handled, 4);
+    assert!(!path.exists(), "socket file must be removed on shutdown");
+    drop(stalled);
+    println!("{}", "IPC round trip verified.".green());
+
+    Ok(())
+}
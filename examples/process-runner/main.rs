@@ -0,0 +1,76 @@
+mod runner;
+
+use std::time::Duration;
+
+use runner::{Job, Outcome, Stream};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let jobs = vec![
+        Job::new("greet", "sh", &["-c", "echo hello; echo oops >&2"]),
+        // Pipe data into the child's stdin.
+        Job::new("upper", "tr", &["a-z", "A-Z"])
+            .stdin("piped input\nsecond line\n"),
+        // stdout and stderr lines are streamed as they are produced.
+        Job::new(
+            "ticker",
+            "sh",
+            &[
+                "-c",
+                "for i in 1 2 3; do echo tick $i; echo tock $i >&2; sleep 0.1; done",
+            ],
+        ),
+        Job::new("fail", "sh", &["-c", "echo failing; exit 3"]),
+        // Killed once the timeout expires.
+        Job::new("sleepy", "sleep", &["30"])
+            .timeout(Duration::from_millis(300)),
+        Job::new("missing", "this-command-does-not-exist", &[]),
+    ];
+
+    let results = runner::run_all(jobs).await;
+    runner::print_report(&results);
+
+    // Verify the report
+    let mut failed_to_spawn = 0;
+    for result in &results {
+        let Ok(r) = result else {
+            failed_to_spawn += 1;
+            continue;
+        };
+        match r.name.as_str() {
+            "greet" => {
+                assert_eq!(r.output(Stream::Stdout), ["hello"]);
+                assert_eq!(r.output(Stream::Stderr), ["oops"]);
+            }
+            "upper" => assert_eq!(
+                r.output(Stream::Stdout),
+                ["PIPED INPUT", "SECOND LINE"]
+            ),
+            "ticker" => {
+                assert_eq!(
+                    r.output(Stream::Stdout),
+                    ["tick 1", "tick 2", "tick 3"]
+                );
+                assert_eq!(
+                    r.output(Stream::Stderr),
+                    ["tock 1", "tock 2", "tock 3"]
+                );
+            }
+            "fail" => {
+                let Outcome::Exited(status) = r.outcome else {
+                    panic!("fail should exit on its own");
+                };
+                assert_eq!(status.code(), Some(3));
+            }
+            "sleepy" => {
+                assert_eq!(r.outcome, Outcome::TimedOut);
+                assert!(r.elapsed < Duration::from_secs(5));
+            }
+            other => panic!("unexpected job {}", other),
+        }
+    }
+    assert_eq!(failed_to_spawn, 1);
+    assert_eq!(results.len(), 6);
+
+    Ok(())
+}
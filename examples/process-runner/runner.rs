@@ -0,0 +1,233 @@
+use std::{
+    fmt,
+    process::{ExitStatus, Stdio},
+    time::Duration,
+};
+
+use anyhow::Context;
+use colored::Colorize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
+    process::Command,
+    sync::mpsc,
+    task::JoinSet,
+    time::{Instant, timeout},
+};
+
+/// How long we keep reading output after the process exited. A grandchild
+/// that inherited the pipes may keep them open long after its parent is gone.
+const OUTPUT_GRACE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// A command to run, with optional input for its stdin and a time limit.
+pub struct Job {
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub stdin: Option<Vec<u8>>,
+    pub timeout: Duration,
+}
+
+impl Job {
+    pub fn new(name: &str, program: &str, args: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            stdin: None,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    pub fn stdin(mut self, input: impl Into<Vec<u8>>) -> Self {
+        self.stdin = Some(input.into());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Exited(ExitStatus),
+    /// The time limit expired and the process was killed.
+    TimedOut,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Exited(status) if status.success() => {
+                write!(f, "{}", "ok".green())
+            }
+            Outcome::Exited(status) => {
+                write!(f, "{}", status.to_string().red())
+            }
+            Outcome::TimedOut => write!(f, "{}", "timed out (killed)".yellow()),
+        }
+    }
+}
+
+pub struct JobResult {
+    pub name: String,
+    pub outcome: Outcome,
+    /// Every line in the order it was received, tagged with its stream.
+    pub lines: Vec<(Stream, String)>,
+    pub elapsed: Duration,
+}
+
+impl JobResult {
+    pub fn output(&self, stream: Stream) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter(|(s, _)| *s == stream)
+            .map(|(_, line)| line.as_str())
+            .collect()
+    }
+}
+
+/// The same `read_line` loop as in `io-buffered.rs`, except that every line is
+/// forwarded to a channel as soon as it has been read.
+async fn forward_lines<R>(
+    reader: R,
+    stream: Stream,
+    tx: mpsc::UnboundedSender<(Stream, String)>,
+) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    let mut line_buffer = String::new();
+    while reader.read_line(&mut line_buffer).await? > 0 {
+        let line = line_buffer.trim_end_matches(['\r', '\n']).to_string();
+        if tx.send((stream, line)).is_err() {
+            break; // nobody is listening anymore
+        }
+        line_buffer.clear();
+    }
+    Ok(())
+}
+
+fn print_line(job: &str, stream: Stream, line: &str) {
+    match stream {
+        Stream::Stdout => println!("[{}] {}", job.cyan(), line),
+        Stream::Stderr => println!("[{}] {}", job.cyan(), line.red()),
+    }
+}
+
+/// Runs a single job, printing its output line by line while it runs.
+pub async fn run(job: Job) -> anyhow::Result<JobResult> {
+    let start = Instant::now();
+    let mut child = Command::new(&job.program)
+        .args(&job.args)
+        .stdin(if job.stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Never leave an orphan behind if this future is dropped.
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| {
+            format!("{}: cannot spawn {:?}", job.name, job.program)
+        })?;
+
+    // Feed stdin from its own task. Dropping the handle closes the pipe, which
+    // is how the child sees EOF. Writing and reading concurrently avoids a
+    // deadlock when the child fills its stdout pipe before consuming stdin.
+    if let (Some(input), Some(mut stdin)) = (job.stdin, child.stdin.take()) {
+        tokio::spawn(async move {
+            let _ = stdin.write_all(&input).await;
+        });
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(forward_lines(stdout, Stream::Stdout, tx.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward_lines(stderr, Stream::Stderr, tx));
+    }
+
+    let wait = async {
+        match timeout(job.timeout, child.wait()).await {
+            Ok(status) => anyhow::Ok(Outcome::Exited(status?)),
+            Err(_) => {
+                // SIGKILL on Unix; `kill` also waits for the process to exit.
+                child.kill().await?;
+                Ok(Outcome::TimedOut)
+            }
+        }
+    };
+    tokio::pin!(wait);
+
+    let mut lines = Vec::new();
+    let outcome = loop {
+        tokio::select! {
+            Some((stream, line)) = rx.recv() => {
+                print_line(&job.name, stream, &line);
+                lines.push((stream, line));
+            }
+            outcome = &mut wait => break outcome?,
+        }
+    };
+
+    // Pick up whatever is still buffered in the pipes.
+    let _ = timeout(OUTPUT_GRACE, async {
+        while let Some((stream, line)) = rx.recv().await {
+            print_line(&job.name, stream, &line);
+            lines.push((stream, line));
+        }
+    })
+    .await;
+
+    Ok(JobResult {
+        name: job.name,
+        outcome,
+        lines,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Runs all jobs concurrently and returns their results in completion order.
+pub async fn run_all(jobs: Vec<Job>) -> Vec<anyhow::Result<JobResult>> {
+    let mut set = JoinSet::new();
+    for job in jobs {
+        set.spawn(run(job));
+    }
+
+    let mut results = Vec::new();
+    while let Some(result) = set.join_next().await {
+        results.push(result.map_err(anyhow::Error::from).and_then(|r| r));
+    }
+    results
+}
+
+pub fn print_report(results: &[anyhow::Result<JobResult>]) {
+    println!(
+        "\n{:<12} {:<24} {:>10} {:>6}",
+        "job", "outcome", "elapsed", "lines"
+    );
+    for result in results {
+        match result {
+            Ok(r) => println!(
+                "{:<12} {:<24} {:>10.1?} {:>6}",
+                r.name,
+                r.outcome.to_string(),
+                r.elapsed,
+                r.lines.len()
+            ),
+            Err(err) => println!("{}", format!("{:#}", err).red()),
+        }
+    }
+}
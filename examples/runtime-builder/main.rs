@@ -0,0 +1,185 @@
+mod runtimes;
+
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use tokio::{
+    task::{self, JoinSet, LocalSet},
+    time::sleep,
+};
+
+use runtimes::{current_thread, multi_thread, thread_name};
+
+/// Every task of a current-thread runtime runs on the thread that called
+/// `block_on`. Only `spawn_blocking` moves work to another thread.
+fn current_thread_flavor() -> anyhow::Result<()> {
+    let runtime = current_thread()?;
+    let main = thread::current().id();
+
+    let (tasks, blocking) = runtime.block_on(async {
+        let mut set = JoinSet::new();
+        for i in 0..10 {
+            set.spawn(async move {
+                sleep(Duration::from_millis(10 - i)).await;
+                thread::current().id()
+            });
+        }
+        let tasks = set.join_all().await;
+        let blocking = task::spawn_blocking(|| thread::current().id()).await;
+        (tasks, blocking)
+    });
+    assert!(tasks.iter().all(|&id| id == main));
+    assert_ne!(blocking?, main);
+    println!(
+        "{} 10 tasks ran on the main thread",
+        "current_thread:".green()
+    );
+    Ok(())
+}
+
+/// Tasks run on the named worker threads, never more than configured, and
+/// `spawn_blocking` is limited to the configured number of threads.
+fn multi_thread_flavor() -> anyhow::Result<()> {
+    let (runtime, tracker) = multi_thread("api", 2, 2)?;
+
+    let workers = runtime.block_on(async {
+        let mut set = JoinSet::new();
+        for _ in 0..100 {
+            set.spawn(async {
+                task::yield_now().await;
+                thread_name()
+            });
+        }
+        set.join_all().await.into_iter().collect::<HashSet<_>>()
+    });
+    assert!(workers.len() <= 2, "{:?}", workers);
+    assert!(workers.iter().all(|name| name.starts_with("api-")));
+    assert_eq!(tracker.started(), 2);
+
+    // Six blocking jobs, two threads: they run two at a time.
+    let running = Arc::new(AtomicUsize::new(0));
+    let most = Arc::new(AtomicUsize::new(0));
+    let start = Instant::now();
+    runtime.block_on(async {
+        let mut set = JoinSet::new();
+        for _ in 0..6 {
+            let running = running.clone();
+            let most = most.clone();
+            set.spawn_blocking(move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(50));
+                running.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        set.join_all().await;
+    });
+    assert_eq!(most.load(Ordering::SeqCst), 2);
+    assert!(start.elapsed() >= Duration::from_millis(150));
+    assert_eq!(tracker.started(), 4);
+
+    drop(runtime);
+    assert_eq!(tracker.stopped(), tracker.started());
+    println!(
+        "{} tasks on {:?}, at most 2 blocking jobs at once, {} threads \
+         started and stopped",
+        "multi_thread:".green(),
+        workers,
+        tracker.started()
+    );
+    Ok(())
+}
+
+/// `Rc` and `RefCell` are not `Send`, so `tokio::spawn` refuses them.
+/// `spawn_local` takes them, and runs them on the thread of the `LocalSet`,
+/// even inside a multi-threaded runtime.
+fn local_set() -> anyhow::Result<()> {
+    let (runtime, _tracker) = multi_thread("local", 2, 1)?;
+    let main = thread::current().id();
+    let counter = Rc::new(RefCell::new(Vec::new()));
+
+    let local = LocalSet::new();
+    let threads = local.block_on(&runtime, async {
+        let mut handles = Vec::new();
+        for i in 0..5 {
+            let counter = counter.clone();
+            handles.push(task::spawn_local(async move {
+                sleep(Duration::from_millis(5)).await;
+                counter.borrow_mut().push(i);
+                thread::current().id()
+            }));
+        }
+        // A regular task still runs on a worker, and can hand results back.
+        let worker = tokio::spawn(async { thread_name() }).await?;
+        assert!(worker.starts_with("local-"));
+
+        let mut threads = Vec::new();
+        for handle in handles {
+            threads.push(handle.await?);
+        }
+        anyhow::Ok(threads)
+    })?;
+    assert!(threads.iter().all(|&id| id == main));
+    let mut values = Rc::try_unwrap(counter).unwrap().into_inner();
+    values.sort();
+    assert_eq!(values, [0, 1, 2, 3, 4]);
+    println!(
+        "{} 5 !Send tasks ran on the main thread",
+        "LocalSet:".green()
+    );
+    Ok(())
+}
+
+/// Two runtimes side by side, each with its own threads. A task on one can
+/// spawn onto the other through its `Handle`, and await the result.
+fn side_by_side() -> anyhow::Result<()> {
+    let (alpha, alpha_threads) = multi_thread("alpha", 2, 1)?;
+    let (beta, beta_threads) = multi_thread("beta", 1, 1)?;
+    let beta_handle = beta.handle().clone();
+
+    let (here, there) = alpha.block_on(async move {
+        let here = tokio::spawn(async { thread_name() }).await?;
+        let there = beta_handle.spawn(async { thread_name() }).await?;
+        anyhow::Ok((here, there))
+    })?;
+    assert!(here.starts_with("alpha-"), "{}", here);
+    assert!(there.starts_with("beta-"), "{}", there);
+
+    // A runtime can also be driven from another thread entirely.
+    let from_thread = thread::spawn(move || {
+        beta.block_on(async { tokio::spawn(async { thread_name() }).await })
+    })
+    .join()
+    .unwrap()?;
+    assert!(from_thread.starts_with("beta-"));
+
+    // `beta` was dropped at the end of that thread, `alpha` is dropped
+    // here. Dropping a runtime stops all its threads.
+    drop(alpha);
+    assert_eq!(alpha_threads.started(), alpha_threads.stopped());
+    assert_eq!(beta_threads.started(), beta_threads.stopped());
+    println!(
+        "{} alpha ran on {}, beta on {}",
+        "side by side:".green(),
+        here,
+        there
+    );
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    current_thread_flavor()?;
+    multi_thread_flavor()?;
+    local_set()?;
+    side_by_side()
+}
@@ -0,0 +1,72 @@
+use std::{
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use tokio::runtime::{Builder, Runtime};
+
+/// The name of the thread running the caller, or `"unnamed"`.
+pub fn thread_name() -> String {
+    thread::current().name().unwrap_or("unnamed").to_string()
+}
+
+/// Counts the threads a runtime starts and stops, through the
+/// `on_thread_start` and `on_thread_stop` hooks.
+#[derive(Debug, Default)]
+pub struct ThreadTracker {
+    started: AtomicUsize,
+    stopped: AtomicUsize,
+}
+
+impl ThreadTracker {
+    pub fn started(&self) -> usize {
+        self.started.load(Ordering::SeqCst)
+    }
+
+    pub fn stopped(&self) -> usize {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+/// A runtime that runs everything on the thread that calls `block_on`.
+pub fn current_thread() -> io::Result<Runtime> {
+    Builder::new_current_thread().enable_all().build()
+}
+
+/// A multi-threaded runtime with at most `workers` worker threads and
+/// `blocking` threads for `spawn_blocking`. Its threads are named
+/// `<name>-0`, `<name>-1`, ...; workers and blocking threads share the
+/// naming function, so the numbers say in which order they started.
+pub fn multi_thread(
+    name: &'static str,
+    workers: usize,
+    blocking: usize,
+) -> io::Result<(Runtime, Arc<ThreadTracker>)> {
+    let tracker = Arc::new(ThreadTracker::default());
+    let next_id = AtomicUsize::new(0);
+    let runtime = Builder::new_multi_thread()
+        .worker_threads(workers)
+        .max_blocking_threads(blocking)
+        .thread_name_fn(move || {
+            format!("{}-{}", name, next_id.fetch_add(1, Ordering::SeqCst))
+        })
+        .on_thread_start({
+            let tracker = tracker.clone();
+            move || {
+                tracker.started.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+        .on_thread_stop({
+            let tracker = tracker.clone();
+            move || {
+                tracker.stopped.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+        .enable_all()
+        .build()?;
+    Ok((runtime, tracker))
+}
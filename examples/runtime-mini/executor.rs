@@ -0,0 +1,294 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    mem::ManuallyDrop,
+    pin::Pin,
+    sync::{
+        Arc, Condvar, Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+/// Anything that can be woken, behind an `Arc`. The standard library has
+/// the same idea as `std::task::Wake`; here we build the `Waker` by hand to
+/// show what it is made of.
+pub trait ArcWake: Send + Sync + 'static {
+    fn wake_by_ref(self: &Arc<Self>);
+}
+
+/// Turns an `Arc<W>` into a `Waker`. The `RawWaker` data pointer is the
+/// `Arc`'s pointer, and the vtable functions manage its reference count.
+pub fn waker<W: ArcWake>(arc: Arc<W>) -> Waker {
+    let raw = RawWaker::new(Arc::into_raw(arc).cast(), vtable::<W>());
+    // Safety: the vtable functions below uphold the `RawWaker` contract for
+    // a pointer created by `Arc::into_raw`.
+    unsafe { Waker::from_raw(raw) }
+}
+
+fn vtable<W: ArcWake>() -> &'static RawWakerVTable {
+    &RawWakerVTable::new(
+        clone_waker::<W>,
+        wake::<W>,
+        wake_by_ref::<W>,
+        drop_waker::<W>,
+    )
+}
+
+unsafe fn clone_waker<W: ArcWake>(data: *const ()) -> RawWaker {
+    // A clone is one more reference to the same `Arc`.
+    unsafe { Arc::increment_strong_count(data.cast::<W>()) };
+    RawWaker::new(data, vtable::<W>())
+}
+
+unsafe fn wake<W: ArcWake>(data: *const ()) {
+    // `wake` consumes the waker, so it takes over its reference.
+    let arc = unsafe { Arc::from_raw(data.cast::<W>()) };
+    arc.wake_by_ref();
+}
+
+unsafe fn wake_by_ref<W: ArcWake>(data: *const ()) {
+    // Borrow the `Arc` without touching the reference count.
+    let arc = ManuallyDrop::new(unsafe { Arc::from_raw(data.cast::<W>()) });
+    arc.wake_by_ref();
+}
+
+unsafe fn drop_waker<W: ArcWake>(data: *const ()) {
+    drop(unsafe { Arc::from_raw(data.cast::<W>()) });
+}
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// State shared by the executor and every waker.
+struct Shared {
+    /// Tasks that were woken and wait to be polled.
+    queue: Mutex<VecDeque<Arc<Task>>>,
+    /// Signalled whenever there is something to do.
+    condvar: Condvar,
+    main_woken: AtomicBool,
+    /// Every task that has not completed yet, keyed by its address, so
+    /// `block_on` can drop them when it returns, queued or not.
+    tasks: Mutex<HashMap<usize, Arc<Task>>>,
+    /// Set once `block_on` has returned. Wakers that fire later, from a
+    /// timer thread say, no longer queue their task.
+    closed: AtomicBool,
+}
+
+impl Shared {
+    fn notify(&self) {
+        // Taking the lock makes sure the executor is either still looking at
+        // the queue or already waiting, so the notification is never lost.
+        let _queue = self.queue.lock().unwrap();
+        self.condvar.notify_one();
+    }
+}
+
+/// The waker of the future passed to `block_on`.
+struct MainWaker {
+    shared: Arc<Shared>,
+}
+
+impl ArcWake for MainWaker {
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.shared.main_woken.store(true, Ordering::SeqCst);
+        self.shared.notify();
+    }
+}
+
+struct Task {
+    /// `None` once the task has completed.
+    future: Mutex<Option<BoxFuture>>,
+    /// Already in the queue, so waking it again does nothing.
+    scheduled: AtomicBool,
+    shared: Arc<Shared>,
+}
+
+impl ArcWake for Task {
+    fn wake_by_ref(self: &Arc<Self>) {
+        if self.shared.closed.load(Ordering::SeqCst) {
+            return;
+        }
+        if !self.scheduled.swap(true, Ordering::SeqCst) {
+            self.shared.queue.lock().unwrap().push_back(self.clone());
+            self.shared.condvar.notify_one();
+        }
+    }
+}
+
+impl Task {
+    fn poll(self: &Arc<Self>) {
+        // Cleared before polling: a wake-up during the poll queues the task
+        // again, so it is never missed.
+        self.scheduled.store(false, Ordering::SeqCst);
+        let mut slot = self.future.lock().unwrap();
+        if let Some(future) = slot.as_mut() {
+            let waker = waker(self.clone());
+            let mut cx = Context::from_waker(&waker);
+            if future.as_mut().poll(&mut cx).is_ready() {
+                *slot = None;
+                let key = Arc::as_ptr(self) as usize;
+                self.shared.tasks.lock().unwrap().remove(&key);
+            }
+        }
+    }
+}
+
+thread_local! {
+    /// The executor running on this thread, for `spawn`.
+    static CURRENT: RefCell<Option<Arc<Shared>>> = const { RefCell::new(None) };
+}
+
+/// Registers the executor of `block_on` in `CURRENT`, and shuts it down when
+/// dropped, even if `block_on` unwinds.
+struct Enter {
+    shared: Arc<Shared>,
+}
+
+impl Enter {
+    fn new(shared: Arc<Shared>) -> Self {
+        CURRENT.with(|current| {
+            let previous = current.borrow_mut().replace(shared.clone());
+            assert!(previous.is_none(), "block_on cannot be nested");
+        });
+        Self { shared }
+    }
+}
+
+impl Drop for Enter {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.borrow_mut().take());
+        self.shared.closed.store(true, Ordering::SeqCst);
+        // Tasks reference `shared`, and a parked task is often referenced by
+        // its own future, through a waker stored in a `JoinHandle` for
+        // instance. Dropping every future breaks these cycles. No lock is
+        // held meanwhile: a future's destructor may wake or drop other tasks.
+        let queued = std::mem::take(&mut *self.shared.queue.lock().unwrap());
+        drop(queued);
+        let tasks = std::mem::take(&mut *self.shared.tasks.lock().unwrap());
+        for task in tasks.into_values() {
+            // A task that panicked poisoned its mutex while polling, and this
+            // may run while that panic unwinds: panicking again here would
+            // abort the process.
+            let future = task
+                .future
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            drop(future);
+        }
+    }
+}
+
+/// Runs `future` to completion on the current thread, along with every task
+/// it spawns. Returns as soon as `future` completes, like `#[tokio::main]`:
+/// tasks still running at that point, queued or waiting to be woken, are
+/// dropped.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::new()),
+        condvar: Condvar::new(),
+        main_woken: AtomicBool::new(true),
+        tasks: Mutex::new(HashMap::new()),
+        closed: AtomicBool::new(false),
+    });
+    let _enter = Enter::new(shared.clone());
+
+    let main_waker = waker(Arc::new(MainWaker {
+        shared: shared.clone(),
+    }));
+    let mut cx = Context::from_waker(&main_waker);
+    let mut future = std::pin::pin!(future);
+
+    loop {
+        if shared.main_woken.swap(false, Ordering::SeqCst)
+            && let Poll::Ready(output) = future.as_mut().poll(&mut cx)
+        {
+            return output;
+        }
+
+        // Run every task that is ready. A task woken while this runs is
+        // simply picked up in the same loop. The lock must be released
+        // before polling, as the task may wake itself.
+        loop {
+            let next = shared.queue.lock().unwrap().pop_front();
+            let Some(task) = next else {
+                break;
+            };
+            task.poll();
+        }
+
+        // Nothing to do: sleep until a waker fires, instead of spinning.
+        let queue = shared.queue.lock().unwrap();
+        let _queue = shared
+            .condvar
+            .wait_while(queue, |queue| {
+                queue.is_empty() && !shared.main_woken.load(Ordering::SeqCst)
+            })
+            .unwrap();
+    }
+}
+
+struct JoinState<T> {
+    output: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// Resolves to the output of a spawned task. Dropping it detaches the task,
+/// which keeps running.
+pub struct JoinHandle<T> {
+    state: Arc<Mutex<JoinState<T>>>,
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock().unwrap();
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                // Remember who to wake when the task completes.
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Starts `future` as a new task on the executor of the current thread.
+///
+/// # Panics
+///
+/// When called outside of `block_on`.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let shared = CURRENT
+        .with(|current| current.borrow().clone())
+        .expect("spawn called outside of block_on");
+
+    let state = Arc::new(Mutex::new(JoinState {
+        output: None,
+        waker: None,
+    }));
+    let task_state = state.clone();
+    let task = Arc::new(Task {
+        future: Mutex::new(Some(Box::pin(async move {
+            let output = future.await;
+            let mut state = task_state.lock().unwrap();
+            state.output = Some(output);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }))),
+        scheduled: AtomicBool::new(false),
+        shared: shared.clone(),
+    });
+    let key = Arc::as_ptr(&task) as usize;
+    shared.tasks.lock().unwrap().insert(key, task.clone());
+    // A new task is ready to run right away.
+    task.wake_by_ref();
+    JoinHandle { state }
+}
@@ -0,0 +1,234 @@
+mod executor;
+mod timer;
+
+use std::{
+    panic,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    task::{Context, Poll},
+    thread,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+
+use executor::{block_on, spawn};
+use timer::sleep;
+
+/// Wraps a future and counts how often it is polled.
+struct Counted<F> {
+    inner: Pin<Box<F>>,
+    polls: Arc<AtomicUsize>,
+}
+
+fn counted<F: Future>(inner: F) -> (Counted<F>, Arc<AtomicUsize>) {
+    let polls = Arc::new(AtomicUsize::new(0));
+    let future = Counted {
+        inner: Box::pin(inner),
+        polls: polls.clone(),
+    };
+    (future, polls)
+}
+
+impl<F: Future> Future for Counted<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        self.polls.fetch_add(1, Ordering::SeqCst);
+        self.inner.as_mut().poll(cx)
+    }
+}
+
+/// Pending the first time, after waking itself `wakes` times.
+struct WakeSelf {
+    wakes: usize,
+    polled: bool,
+}
+
+impl Future for WakeSelf {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.polled {
+            return Poll::Ready(());
+        }
+        self.polled = true;
+        for _ in 0..self.wakes {
+            cx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    }
+}
+
+/// The `basics.rs` program, on our own executor.
+fn basics() {
+    block_on(async {
+        println!("before sleep");
+        sleep(Duration::from_secs(1)).await;
+        println!("after sleep");
+    });
+}
+
+fn spawn_and_join() {
+    let start = Instant::now();
+    let total = block_on(async {
+        let handles: Vec<_> = (1..=10u64)
+            .map(|n| {
+                spawn(async move {
+                    sleep(Duration::from_millis(20 * n)).await;
+                    n * n
+                })
+            })
+            .collect();
+        let mut total = 0;
+        for handle in handles {
+            total += handle.await;
+        }
+        total
+    });
+    assert_eq!(total, 385);
+    // The sleeps overlap on the single thread: the total time is the
+    // longest sleep, not the sum of all of them.
+    let elapsed = start.elapsed();
+    assert!(elapsed < Duration::from_millis(600), "{:?}", elapsed);
+    println!(
+        "{} 10 tasks joined in {:?}",
+        "spawn and join:".green(),
+        elapsed
+    );
+}
+
+fn wakeups() {
+    // Woken once, by the timer thread: exactly two polls, no busy polling.
+    let (future, polls) = counted(sleep(Duration::from_millis(50)));
+    block_on(future);
+    assert_eq!(polls.load(Ordering::SeqCst), 2);
+
+    // Many wakes before the task runs again still cause only one poll.
+    block_on(async {
+        let (future, polls) = counted(WakeSelf {
+            wakes: 100,
+            polled: false,
+        });
+        spawn(future).await;
+        assert_eq!(polls.load(Ordering::SeqCst), 2);
+    });
+
+    // The timer thread wakes the task, but the task still runs on the
+    // thread of `block_on`. Its `JoinHandle` then wakes the main future.
+    let (future, polls) = counted(async {
+        let handle = spawn(async {
+            sleep(Duration::from_millis(10)).await;
+            thread::current().id()
+        });
+        assert_eq!(handle.await, thread::current().id());
+    });
+    block_on(future);
+    // Once to start, once when the task completed.
+    assert_eq!(polls.load(Ordering::SeqCst), 2);
+
+    // A dropped handle detaches the task, which still runs.
+    let done = Arc::new(AtomicBool::new(false));
+    block_on({
+        let done = done.clone();
+        async move {
+            drop(spawn(async move {
+                sleep(Duration::from_millis(10)).await;
+                done.store(true, Ordering::SeqCst);
+            }));
+            sleep(Duration::from_millis(50)).await;
+        }
+    });
+    assert!(done.load(Ordering::SeqCst));
+
+    // Tasks that are ready run in the order they were woken.
+    let order = block_on(async {
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let handles: Vec<_> = (0..5)
+            .map(|n| {
+                let order = order.clone();
+                spawn(async move { order.lock().unwrap().push(n) })
+            })
+            .collect();
+        for handle in handles {
+            handle.await;
+        }
+        Arc::try_unwrap(order).unwrap().into_inner().unwrap()
+    });
+    assert_eq!(order, [0, 1, 2, 3, 4]);
+
+    // Keep the expected panic from `spawn` outside `block_on` quiet, and
+    // only that one.
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let outside = panic::catch_unwind(|| spawn(async {}));
+    panic::set_hook(default_hook);
+    assert!(outside.is_err());
+    println!("{}", "wakeups ok".green());
+}
+
+/// Sets its flag when dropped.
+struct DropFlag(Arc<AtomicBool>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+fn shutdown() {
+    // A task waiting on a `JoinHandle` is referenced by its own future,
+    // through the waker stored in the handle. `block_on` still drops it.
+    let dropped = Arc::new(AtomicBool::new(false));
+    block_on({
+        let flag = DropFlag(dropped.clone());
+        async move {
+            spawn(async move {
+                let _flag = flag;
+                spawn(std::future::pending::<()>()).await;
+            });
+            sleep(Duration::from_millis(10)).await;
+        }
+    });
+    assert!(dropped.load(Ordering::SeqCst));
+
+    // So is a task waiting on a timer that fires after `block_on` returned.
+    let dropped = Arc::new(AtomicBool::new(false));
+    block_on({
+        let flag = DropFlag(dropped.clone());
+        async move {
+            spawn(async move {
+                let _flag = flag;
+                sleep(Duration::from_millis(50)).await;
+            });
+        }
+    });
+    assert!(dropped.load(Ordering::SeqCst));
+    // The timer thread still fires; the closed executor ignores the wake-up.
+    thread::sleep(Duration::from_millis(100));
+
+    // A panic inside `block_on` leaves the thread free for the next one.
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let panicked = panic::catch_unwind(|| block_on(async { panic!() }));
+    assert!(panicked.is_err());
+    // So does a panic in a spawned task, which unwinds out of `block_on`
+    // with the task's mutex poisoned.
+    let panicked = panic::catch_unwind(|| {
+        block_on(async { spawn(async { panic!() }).await })
+    });
+    panic::set_hook(default_hook);
+    assert!(panicked.is_err());
+    assert_eq!(block_on(async { spawn(async { 1 }).await }), 1);
+    println!("{}", "shutdown ok".green());
+}
+
+fn main() {
+    wakeups();
+    shutdown();
+    spawn_and_join();
+    basics();
+}
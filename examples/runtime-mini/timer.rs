@@ -0,0 +1,63 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Default)]
+struct State {
+    done: bool,
+    waker: Option<Waker>,
+}
+
+/// A future that completes after a delay, for our executor. It works in
+/// any executor, since all it needs is the `Waker`.
+pub struct Sleep {
+    deadline: Instant,
+    state: Arc<Mutex<State>>,
+    started: bool,
+}
+
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        deadline: Instant::now() + duration,
+        state: Arc::default(),
+        started: false,
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.done {
+            return Poll::Ready(());
+        }
+        // Always store the latest waker: the future may have moved to
+        // another task since the last poll.
+        state.waker = Some(cx.waker().clone());
+        drop(state);
+
+        if !self.started {
+            self.started = true;
+            // One thread per timer keeps this short. A real runtime has a
+            // single timer driver for all timers instead.
+            let state = self.state.clone();
+            let deadline = self.deadline;
+            thread::spawn(move || {
+                thread::sleep(
+                    deadline.saturating_duration_since(Instant::now()),
+                );
+                let mut state = state.lock().unwrap();
+                state.done = true;
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            });
+        }
+        Poll::Pending
+    }
+}
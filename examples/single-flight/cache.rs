@@ -0,0 +1,112 @@
+use std::{collections::HashMap, hash::Hash, sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::single_flight::SingleFlight;
+
+/// Keeps computed values for `ttl`, and computes a missing or expired value
+/// once, however many tasks ask for it at the same time.
+///
+/// Only successes are kept. An error is shared with the callers that waited
+/// for it, and the next request tries again.
+pub struct Cache<K, V, E> {
+    entries: Mutex<Entries<K, V>>,
+    flight: SingleFlight<K, Result<V, E>>,
+    ttl: Duration,
+}
+
+struct Entries<K, V> {
+    values: HashMap<K, (V, Instant)>,
+    /// Bumped by every `invalidate`. A value computed across an
+    /// invalidation may be stale, so it is returned but not kept.
+    generation: u64,
+    /// Expired values are dropped when read, and swept at most once per
+    /// `ttl` for keys that are never asked for again.
+    next_sweep: Instant,
+}
+
+impl<K, V, E> Cache<K, V, E>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    E: Clone,
+{
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                values: HashMap::new(),
+                generation: 0,
+                next_sweep: Instant::now() + ttl,
+            }),
+            flight: SingleFlight::new(),
+            ttl,
+        }
+    }
+
+    /// The cached value for `key`, or else the one `f` computes.
+    pub async fn get<F, Fut>(&self, key: K, f: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        if let Some(value) = self.fresh(&key) {
+            return Ok(value);
+        }
+        self.flight
+            .get(key.clone(), || async {
+                // Another leader may have filled the entry between our
+                // lookup and now.
+                if let Some(value) = self.fresh(&key) {
+                    return Ok(value);
+                }
+                let generation = self.entries.lock().unwrap().generation;
+                let value = f().await?;
+                self.insert(key.clone(), value.clone(), generation);
+                Ok(value)
+            })
+            .await
+    }
+
+    /// The value for `key` if it has not expired. An expired one is removed.
+    fn fresh(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.values.get(key) {
+            Some((value, expires)) if Instant::now() < *expires => {
+                Some(value.clone())
+            }
+            Some(_) => {
+                entries.values.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Keeps `value`, unless the cache was invalidated since `generation`.
+    fn insert(&self, key: K, value: V, generation: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation != generation {
+            return;
+        }
+        let now = Instant::now();
+        if now >= entries.next_sweep {
+            entries.values.retain(|_, (_, expires)| now < *expires);
+            entries.next_sweep = now + self.ttl;
+        }
+        entries.values.insert(key, (value, now + self.ttl));
+    }
+
+    /// Drops `key`, so the next request computes it again. A computation
+    /// already running when this is called does not put its value back.
+    pub fn invalidate(&self, key: &K) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.values.remove(key);
+        entries.generation += 1;
+    }
+
+    /// How many values are held, expired ones that were not swept yet
+    /// included.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().values.len()
+    }
+}
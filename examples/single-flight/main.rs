@@ -0,0 +1,295 @@
+mod cache;
+mod single_flight;
+
+use std::{
+    panic,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{
+    task::{self, JoinSet},
+    time::{Instant, sleep},
+};
+
+use cache::Cache;
+use single_flight::SingleFlight;
+
+const WORK: Duration = Duration::from_millis(100);
+
+/// Stands in for an expensive call, such as a database query: counts the
+/// calls and takes `WORK` to answer.
+#[derive(Default)]
+struct Backend {
+    calls: AtomicUsize,
+}
+
+impl Backend {
+    async fn square(&self, n: u64) -> u64 {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        sleep(WORK).await;
+        n * n
+    }
+
+    async fn fail(&self) -> Result<u64, String> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        sleep(WORK).await;
+        Err("backend down".to_string())
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+async fn coalescing() {
+    let flight = Arc::new(SingleFlight::new());
+    let backend = Arc::new(Backend::default());
+    let start = Instant::now();
+
+    let mut set = JoinSet::new();
+    for i in 0..100 {
+        let (flight, backend) = (flight.clone(), backend.clone());
+        let key = i % 4;
+        set.spawn(async move {
+            let value = flight.get(key, || backend.square(key)).await;
+            (key, value)
+        });
+    }
+    while let Some(result) = set.join_next().await {
+        let (key, value) = result.unwrap();
+        assert_eq!(value, key * key);
+    }
+    // One computation per key, all of them at the same time.
+    assert_eq!(backend.calls(), 4);
+    assert_eq!(start.elapsed(), WORK);
+    assert_eq!(flight.in_flight(), 0);
+
+    // A request that does not overlap computes the value again.
+    assert_eq!(flight.get(3, || backend.square(3)).await, 9);
+    assert_eq!(backend.calls(), 5);
+}
+
+async fn shared_errors() {
+    let flight = Arc::new(SingleFlight::new());
+    let backend = Arc::new(Backend::default());
+
+    let mut set = JoinSet::new();
+    for _ in 0..10 {
+        let (flight, backend) = (flight.clone(), backend.clone());
+        set.spawn(async move { flight.get("user:1", || backend.fail()).await });
+    }
+    for result in set.join_all().await {
+        assert_eq!(result, Err("backend down".to_string()));
+    }
+    assert_eq!(backend.calls(), 1);
+}
+
+/// Spawns `n` callers for `"report"` while the leader is running, each with a
+/// computation that answers with its own number.
+fn waiters(
+    flight: &Arc<SingleFlight<&'static str, usize>>,
+    n: usize,
+) -> JoinSet<usize> {
+    let mut set = JoinSet::new();
+    for i in 1..=n {
+        let flight = flight.clone();
+        set.spawn(async move {
+            flight
+                .get("report", || async move {
+                    sleep(WORK).await;
+                    i
+                })
+                .await
+        });
+    }
+    set
+}
+
+async fn leader_cancelled() {
+    let flight = Arc::new(SingleFlight::new());
+    let start = Instant::now();
+
+    let leader = tokio::spawn({
+        let flight = flight.clone();
+        async move {
+            flight
+                .get("report", || async {
+                    sleep(WORK).await;
+                    0
+                })
+                .await
+        }
+    });
+    task::yield_now().await;
+    let set = waiters(&flight, 5);
+
+    sleep(WORK / 2).await;
+    leader.abort();
+    assert!(leader.await.unwrap_err().is_cancelled());
+
+    // One waiter took over and started from scratch; the others got its
+    // value.
+    let values = set.join_all().await;
+    assert!(values[0] != 0 && values.iter().all(|v| *v == values[0]));
+    assert_eq!(start.elapsed(), WORK / 2 + WORK);
+    assert_eq!(flight.in_flight(), 0);
+}
+
+async fn leader_panicked() {
+    let flight = Arc::new(SingleFlight::new());
+
+    let leader = tokio::spawn({
+        let flight = flight.clone();
+        async move {
+            flight
+                .get("report", || async {
+                    sleep(WORK / 2).await;
+                    panic!("single-flight: expected panic");
+                })
+                .await
+        }
+    });
+    task::yield_now().await;
+    let set = waiters(&flight, 5);
+
+    assert!(leader.await.unwrap_err().is_panic());
+    let values = set.join_all().await;
+    assert!(values.iter().all(|v| *v == values[0]));
+}
+
+/// No leader and no waiter: the next caller for the key takes over.
+async fn leader_cancelled_alone() {
+    let flight = SingleFlight::new();
+    let pending = flight.get(1, || sleep(WORK));
+    assert!(tokio::time::timeout(WORK / 2, pending).await.is_err());
+    assert_eq!(flight.in_flight(), 0);
+    flight.get(1, || async {}).await;
+    assert_eq!(flight.in_flight(), 0);
+}
+
+async fn cache_ttl() {
+    let cache = Arc::new(Cache::new(Duration::from_secs(1)));
+    let backend = Arc::new(Backend::default());
+    let get = |key: u64| {
+        let (cache, backend) = (cache.clone(), backend.clone());
+        async move {
+            cache
+                .get(key, || async {
+                    Ok::<_, String>(backend.square(key).await)
+                })
+                .await
+        }
+    };
+
+    // A cold key is computed once, for everyone asking.
+    let start = Instant::now();
+    let mut set = JoinSet::new();
+    for _ in 0..10 {
+        set.spawn(get(3));
+    }
+    for value in set.join_all().await {
+        assert_eq!(value, Ok(9));
+    }
+    assert_eq!(backend.calls(), 1);
+    assert_eq!(start.elapsed(), WORK);
+
+    // Then it comes from the cache, at once.
+    let start = Instant::now();
+    assert_eq!(get(3).await, Ok(9));
+    assert_eq!(start.elapsed(), Duration::ZERO);
+    assert_eq!(backend.calls(), 1);
+
+    // Expired: computed again, once.
+    sleep(Duration::from_secs(1)).await;
+    let mut set = JoinSet::new();
+    for _ in 0..10 {
+        set.spawn(get(3));
+    }
+    set.join_all().await;
+    assert_eq!(backend.calls(), 2);
+
+    cache.invalidate(&3);
+    assert_eq!(get(3).await, Ok(9));
+    assert_eq!(backend.calls(), 3);
+
+    // Errors are not kept.
+    assert!(cache.get(4, || backend.fail()).await.is_err());
+    assert_eq!(get(4).await, Ok(16));
+    assert_eq!(backend.calls(), 5);
+
+    // Invalidated while it was computed: the value is returned, but may be
+    // stale, so it is not kept.
+    let pending = tokio::spawn(get(5));
+    sleep(WORK / 2).await;
+    cache.invalidate(&5);
+    assert_eq!(pending.await.unwrap(), Ok(25));
+    assert_eq!(get(5).await, Ok(25));
+    assert_eq!(backend.calls(), 7);
+
+    // Expired values of keys nobody asks for again are swept.
+    assert_eq!(cache.len(), 3);
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(get(6).await, Ok(36));
+    assert_eq!(cache.len(), 1);
+}
+
+fn fib(v: usize) -> usize {
+    match v {
+        0 => 0,
+        1 => 1,
+        _ => fib(v - 1) + fib(v - 2),
+    }
+}
+
+/// `task-management-joinset-blocking.rs`, with repeated requests: each
+/// distinct `fib(n)` runs once.
+async fn fib_requests() {
+    let cache = Arc::new(Cache::new(Duration::from_secs(60)));
+    let computed = Arc::new(AtomicUsize::new(0));
+
+    let mut set = JoinSet::new();
+    for value in [25, 28, 25, 28, 28, 20, 25] {
+        let (cache, computed) = (cache.clone(), computed.clone());
+        set.spawn(async move {
+            let result = cache
+                .get(value, || async {
+                    computed.fetch_add(1, Ordering::SeqCst);
+                    task::spawn_blocking(move || fib(value))
+                        .await
+                        .map_err(|e| e.to_string())
+                })
+                .await;
+            (value, result)
+        });
+    }
+    while let Some(result) = set.join_next().await {
+        let (value, result) = result.unwrap();
+        println!("fib({}) = {:?}", value, result);
+        assert_eq!(result, Ok(fib(value)));
+    }
+    assert_eq!(computed.load(Ordering::SeqCst), 3);
+}
+
+// The clock is paused, so the timings asserted above are exact.
+#[tokio::main(flavor = "current_thread", start_paused = true)]
+async fn main() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let message = info.payload().downcast_ref::<&str>();
+        if message != Some(&"single-flight: expected panic") {
+            default_hook(info);
+        }
+    }));
+
+    coalescing().await;
+    shared_errors().await;
+    leader_cancelled().await;
+    leader_panicked().await;
+    leader_cancelled_alone().await;
+    cache_ttl().await;
+    fib_requests().await;
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    pin::pin,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::Notify;
+
+enum State<V> {
+    /// A caller, the leader, is computing the value.
+    Running,
+    /// The leader was cancelled or panicked; the next waiter to notice takes
+    /// over.
+    Vacant,
+    Done(V),
+}
+
+/// One computation, shared by every caller that asked for its key meanwhile.
+struct Call<V> {
+    state: Mutex<State<V>>,
+    changed: Notify,
+}
+
+/// Deduplicates concurrent requests for the same key: while a value is being
+/// computed, later callers wait for it instead of computing it again.
+///
+/// Only requests that overlap are merged. Once the value is handed out, the
+/// next request for the key computes it anew; see `Cache` to keep it.
+pub struct SingleFlight<K, V> {
+    calls: Mutex<HashMap<K, Arc<Call<V>>>>,
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The value for `key`: computed with `f` if no other caller is computing
+    /// it, or else the one they compute.
+    ///
+    /// Errors are values too: with `V = Result<T, E>`, every waiter gets the
+    /// leader's error. If the leader is cancelled or panics, one waiter
+    /// becomes the leader and runs its own `f`.
+    pub async fn get<F, Fut>(&self, key: K, f: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        let (call, mut leader) = {
+            let mut calls = self.calls.lock().unwrap();
+            match calls.get(&key) {
+                Some(call) => (call.clone(), false),
+                None => {
+                    let call = Arc::new(Call {
+                        state: Mutex::new(State::Running),
+                        changed: Notify::new(),
+                    });
+                    calls.insert(key.clone(), call.clone());
+                    (call, true)
+                }
+            }
+        };
+
+        loop {
+            if leader {
+                let guard = Leader {
+                    flight: self,
+                    key: &key,
+                    call: &call,
+                    finished: false,
+                };
+                let value = f().await;
+                guard.finish(value.clone());
+                return value;
+            }
+
+            // Register for the notification before looking at the state, so
+            // a change in between is not missed.
+            let mut changed = pin!(call.changed.notified());
+            changed.as_mut().enable();
+            {
+                let mut state = call.state.lock().unwrap();
+                match &*state {
+                    State::Done(value) => return value.clone(),
+                    State::Vacant => {
+                        *state = State::Running;
+                        leader = true;
+                        continue;
+                    }
+                    State::Running => {}
+                }
+            }
+            changed.await;
+        }
+    }
+
+    /// How many keys are being computed right now.
+    pub fn in_flight(&self) -> usize {
+        self.calls.lock().unwrap().len()
+    }
+
+    /// Removes `call` from the map. With `only_if_alone`, only if no waiter
+    /// holds it, so there is no one left to take it over.
+    fn forget(&self, key: &K, call: &Arc<Call<V>>, only_if_alone: bool) {
+        let mut calls = self.calls.lock().unwrap();
+        // New waiters clone the call while holding this lock, so the count
+        // cannot grow meanwhile. One reference is in the map, one is ours.
+        if only_if_alone && Arc::strong_count(call) > 2 {
+            return;
+        }
+        // A later call for the same key may have replaced ours already.
+        if calls.get(key).is_some_and(|c| Arc::ptr_eq(c, call)) {
+            calls.remove(key);
+        }
+    }
+}
+
+/// Held by the leader while it computes. Dropped without `finish`, because
+/// the leader's future was dropped or it panicked, it hands the call over to
+/// the waiters.
+struct Leader<'a, K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    flight: &'a SingleFlight<K, V>,
+    key: &'a K,
+    call: &'a Arc<Call<V>>,
+    finished: bool,
+}
+
+impl<K, V> Leader<'_, K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    fn finish(mut self, value: V) {
+        self.finished = true;
+        *self.call.state.lock().unwrap() = State::Done(value);
+        // New callers start a new computation from now on; the waiters that
+        // already have the call read the value from it.
+        self.flight.forget(self.key, self.call, false);
+        self.call.changed.notify_waiters();
+    }
+}
+
+impl<K, V> Drop for Leader<'_, K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        *self.call.state.lock().unwrap() = State::Vacant;
+        // With no one waiting, nobody would take the call over: remove it,
+        // and the next caller for the key starts a new one.
+        self.flight.forget(self.key, self.call, true);
+        self.call.changed.notify_waiters();
+    }
+}
@@ -0,0 +1,29 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use tokio::time::{Instant, Interval};
+
+/// The ticks of an `Interval`, as a `Stream` that never ends.
+pub struct IntervalStream {
+    interval: Interval,
+}
+
+impl IntervalStream {
+    pub fn new(interval: Interval) -> Self {
+        Self { interval }
+    }
+}
+
+impl Stream for IntervalStream {
+    type Item = Instant;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Instant>> {
+        self.interval.poll_tick(cx).map(Some)
+    }
+}
@@ -0,0 +1,424 @@
+//! Stream combinators written by hand, as `poll_next` state machines.
+//!
+//! Every combinator keeps the streams and futures it wraps in a `Pin<Box<_>>`,
+//! so it never needs to pin a field in place and can be `Unpin` itself, whatever
+//! it wraps. That costs an allocation per combinator, but no `unsafe` and no
+//! `pin-project`.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use tokio::time::{Instant, Sleep, sleep};
+
+use crate::channel_stream;
+
+pub trait StreamExt: Stream {
+    /// Awaits the next item.
+    fn next(&mut self) -> impl Future<Output = Option<Self::Item>>
+    where
+        Self: Unpin,
+    {
+        channel_stream::next(self)
+    }
+
+    /// Applies `f` to every item.
+    fn map<T, F>(self, f: F) -> Map<Self, F>
+    where
+        F: FnMut(Self::Item) -> T,
+        Self: Sized,
+    {
+        Map {
+            stream: Box::pin(self),
+            f,
+        }
+    }
+
+    /// Keeps the items for which `predicate` returns `true`.
+    fn filter<P>(self, predicate: P) -> Filter<Self, P>
+    where
+        P: FnMut(&Self::Item) -> bool,
+        Self: Sized,
+    {
+        Filter {
+            stream: Box::pin(self),
+            predicate,
+        }
+    }
+
+    /// Runs up to `limit` of the futures the stream yields at the same time,
+    /// and yields their outputs in the order they complete.
+    fn buffer_unordered(self, limit: usize) -> BufferUnordered<Self>
+    where
+        Self::Item: Future,
+        Self: Sized,
+    {
+        assert!(limit > 0, "limit must be at least 1");
+        BufferUnordered {
+            stream: Some(Box::pin(self)),
+            running: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Groups items into chunks of up to `max` items. A chunk that is not
+    /// full is yielded anyway `timeout` after its first item arrived.
+    fn chunks_timeout(
+        self,
+        max: usize,
+        timeout: Duration,
+    ) -> ChunksTimeout<Self>
+    where
+        Self: Sized,
+    {
+        assert!(max > 0, "max must be at least 1");
+        ChunksTimeout {
+            stream: Box::pin(self),
+            max,
+            timeout,
+            chunk: Vec::new(),
+            deadline: None,
+            done: false,
+        }
+    }
+
+    /// Yields at most one item per `period`. Items are delayed, not dropped.
+    fn throttle(self, period: Duration) -> Throttle<Self>
+    where
+        Self: Sized,
+    {
+        Throttle {
+            stream: Box::pin(self),
+            period,
+            delay: None,
+        }
+    }
+
+    /// Yields the items of both streams as they arrive, and ends once both
+    /// have ended.
+    fn merge<B>(self, other: B) -> Merge<Self, B>
+    where
+        B: Stream<Item = Self::Item>,
+        Self: Sized,
+    {
+        Merge {
+            a: Some(Box::pin(self)),
+            b: Some(Box::pin(other)),
+            a_first: false,
+        }
+    }
+
+    /// Yields an item only once the stream has been quiet for `quiet`, and
+    /// drops the items that were replaced by a newer one in the meantime.
+    /// When the stream ends, the last item is yielded straight away.
+    fn debounce(self, quiet: Duration) -> Debounce<Self>
+    where
+        Self: Sized,
+    {
+        Debounce {
+            stream: Box::pin(self),
+            quiet,
+            latest: None,
+            delay: None,
+            done: false,
+        }
+    }
+}
+
+impl<S: Stream + ?Sized> StreamExt for S {}
+
+pub struct Map<S, F> {
+    stream: Pin<Box<S>>,
+    f: F,
+}
+
+impl<S, F> Unpin for Map<S, F> {}
+
+impl<S, F, T> Stream for Map<S, F>
+where
+    S: Stream,
+    F: FnMut(S::Item) -> T,
+{
+    type Item = T;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<T>> {
+        let item = ready!(self.stream.as_mut().poll_next(cx));
+        Poll::Ready(item.map(&mut self.f))
+    }
+}
+
+pub struct Filter<S, P> {
+    stream: Pin<Box<S>>,
+    predicate: P,
+}
+
+impl<S, P> Unpin for Filter<S, P> {}
+
+impl<S, P> Stream for Filter<S, P>
+where
+    S: Stream,
+    P: FnMut(&S::Item) -> bool,
+{
+    type Item = S::Item;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<S::Item>> {
+        let this = &mut *self;
+        loop {
+            match ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(item) if !(this.predicate)(&item) => continue,
+                item => return Poll::Ready(item),
+            }
+        }
+    }
+}
+
+pub struct BufferUnordered<S: Stream> {
+    /// `None` once the stream has ended.
+    stream: Option<Pin<Box<S>>>,
+    running: Vec<Pin<Box<S::Item>>>,
+    limit: usize,
+}
+
+impl<S: Stream> Unpin for BufferUnordered<S> {}
+
+impl<S> Stream for BufferUnordered<S>
+where
+    S: Stream,
+    S::Item: Future,
+{
+    type Item = <S::Item as Future>::Output;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        // Top up the running futures first, so they all make progress below.
+        while this.running.len() < this.limit {
+            let Some(stream) = &mut this.stream else {
+                break;
+            };
+            match stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(future)) => {
+                    this.running.push(Box::pin(future))
+                }
+                Poll::Ready(None) => this.stream = None,
+                Poll::Pending => break,
+            }
+        }
+
+        for i in 0..this.running.len() {
+            if let Poll::Ready(output) = this.running[i].as_mut().poll(cx) {
+                drop(this.running.swap_remove(i));
+                return Poll::Ready(Some(output));
+            }
+        }
+
+        if this.stream.is_none() && this.running.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+pub struct ChunksTimeout<S: Stream> {
+    stream: Pin<Box<S>>,
+    max: usize,
+    timeout: Duration,
+    chunk: Vec<S::Item>,
+    /// When the current chunk is due; set when its first item arrives.
+    deadline: Option<Pin<Box<Sleep>>>,
+    done: bool,
+}
+
+impl<S: Stream> Unpin for ChunksTimeout<S> {}
+
+impl<S: Stream> ChunksTimeout<S> {
+    fn take_chunk(&mut self) -> Vec<S::Item> {
+        self.deadline = None;
+        std::mem::take(&mut self.chunk)
+    }
+}
+
+impl<S: Stream> Stream for ChunksTimeout<S> {
+    type Item = Vec<S::Item>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Vec<S::Item>>> {
+        let this = &mut *self;
+        while !this.done {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    if this.chunk.is_empty() {
+                        this.deadline = Some(Box::pin(sleep(this.timeout)));
+                    }
+                    this.chunk.push(item);
+                    if this.chunk.len() == this.max {
+                        return Poll::Ready(Some(this.take_chunk()));
+                    }
+                }
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if this.chunk.is_empty() {
+            return if this.done {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            };
+        }
+        if this.done {
+            return Poll::Ready(Some(this.take_chunk()));
+        }
+        if let Some(deadline) = &mut this.deadline {
+            ready!(deadline.as_mut().poll(cx));
+        }
+        Poll::Ready(Some(this.take_chunk()))
+    }
+}
+
+pub struct Throttle<S> {
+    stream: Pin<Box<S>>,
+    period: Duration,
+    /// Running after an item was yielded: no other item until it completes.
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S> Unpin for Throttle<S> {}
+
+impl<S: Stream> Stream for Throttle<S> {
+    type Item = S::Item;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<S::Item>> {
+        if let Some(delay) = &mut self.delay {
+            ready!(delay.as_mut().poll(cx));
+            self.delay = None;
+        }
+        let item = ready!(self.stream.as_mut().poll_next(cx));
+        if item.is_some() {
+            self.delay = Some(Box::pin(sleep(self.period)));
+        }
+        Poll::Ready(item)
+    }
+}
+
+pub struct Merge<A, B> {
+    /// Each side is `None` once it has ended.
+    a: Option<Pin<Box<A>>>,
+    b: Option<Pin<Box<B>>>,
+    /// Which side is polled first, swapped on every call so a busy stream
+    /// cannot starve the other one.
+    a_first: bool,
+}
+
+impl<A, B> Unpin for Merge<A, B> {}
+
+/// Polls one side of a `Merge`, forgetting it once it has ended.
+fn poll_side<S: Stream>(
+    side: &mut Option<Pin<Box<S>>>,
+    cx: &mut Context<'_>,
+) -> Poll<Option<S::Item>> {
+    let Some(stream) = side else {
+        return Poll::Ready(None);
+    };
+    let item = ready!(stream.as_mut().poll_next(cx));
+    if item.is_none() {
+        *side = None;
+    }
+    Poll::Ready(item)
+}
+
+impl<A, B> Stream for Merge<A, B>
+where
+    A: Stream,
+    B: Stream<Item = A::Item>,
+{
+    type Item = A::Item;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<A::Item>> {
+        let this = &mut *self;
+        this.a_first = !this.a_first;
+        for turn in 0..2 {
+            let poll = if (turn == 0) == this.a_first {
+                poll_side(&mut this.a, cx)
+            } else {
+                poll_side(&mut this.b, cx)
+            };
+            if let Poll::Ready(Some(item)) = poll {
+                return Poll::Ready(Some(item));
+            }
+        }
+        if this.a.is_none() && this.b.is_none() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+pub struct Debounce<S: Stream> {
+    stream: Pin<Box<S>>,
+    quiet: Duration,
+    /// The newest item, waiting for the stream to go quiet.
+    latest: Option<S::Item>,
+    delay: Option<Pin<Box<Sleep>>>,
+    done: bool,
+}
+
+impl<S: Stream> Unpin for Debounce<S> {}
+
+impl<S: Stream> Stream for Debounce<S> {
+    type Item = S::Item;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<S::Item>> {
+        let this = &mut *self;
+        while !this.done {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    this.latest = Some(item);
+                    let deadline = Instant::now() + this.quiet;
+                    match &mut this.delay {
+                        Some(delay) => delay.as_mut().reset(deadline),
+                        None => this.delay = Some(Box::pin(sleep(this.quiet))),
+                    }
+                }
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if this.done {
+            this.delay = None;
+            return Poll::Ready(this.latest.take());
+        }
+        let Some(delay) = &mut this.delay else {
+            return Poll::Pending;
+        };
+        ready!(delay.as_mut().poll(cx));
+        this.delay = None;
+        Poll::Ready(this.latest.take())
+    }
+}
@@ -0,0 +1,201 @@
+mod adapters;
+#[path = "../common/channel_stream.rs"]
+mod channel_stream;
+mod combinators;
+
+use std::{
+    fmt::Debug,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use futures_core::Stream;
+use tokio::{
+    sync::mpsc,
+    time::{Instant, interval, sleep, sleep_until},
+};
+
+use adapters::IntervalStream;
+use channel_stream::ChannelStream;
+use combinators::StreamExt;
+
+/// A stream that yields each value at the given number of milliseconds after
+/// `start`, and ends after the last one.
+fn timed<T>(start: Instant, events: Vec<(u64, T)>) -> ChannelStream<T>
+where
+    T: Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        for (at, value) in events {
+            sleep_until(start + Duration::from_millis(at)).await;
+            tx.send(value).await.unwrap();
+        }
+    });
+    ChannelStream::new(rx)
+}
+
+/// Every item of the stream, with the millisecond it arrived at.
+async fn record<S>(start: Instant, mut stream: S) -> Vec<(u64, S::Item)>
+where
+    S: Stream + Unpin,
+{
+    let mut items = Vec::new();
+    while let Some(item) = stream.next().await {
+        items.push((start.elapsed().as_millis() as u64, item));
+    }
+    items
+}
+
+fn check<T: PartialEq + Debug>(
+    name: &str,
+    got: Vec<(u64, T)>,
+    want: &[(u64, T)],
+) {
+    assert_eq!(got, want, "{}", name);
+    println!("{:<16} {:?}", name, got);
+}
+
+async fn map_filter() {
+    let start = Instant::now();
+    let events = (0..10).map(|i| (i * 10, i)).collect();
+    let squares = timed(start, events).filter(|n| n % 3 == 0).map(|n| n * n);
+    let got = record(start, squares).await;
+    check("map + filter", got, &[(0, 0), (30, 9), (60, 36), (90, 81)]);
+}
+
+async fn intervals() {
+    let start = Instant::now();
+    let mut ticks = IntervalStream::new(interval(Duration::from_millis(25)));
+    let mut got = Vec::new();
+    for _ in 0..4 {
+        let tick = ticks.next().await.unwrap();
+        got.push(((tick - start).as_millis() as u64, ()));
+    }
+    // The first tick completes immediately.
+    check("interval", got, &[(0, ()), (25, ()), (50, ()), (75, ())]);
+}
+
+async fn buffer_unordered() {
+    let start = Instant::now();
+    let running = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = mpsc::channel(16);
+    for (name, ms) in [("a", 50), ("b", 10), ("c", 20), ("d", 5)] {
+        let (running, peak) = (running.clone(), peak.clone());
+        tx.send(async move {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            sleep(Duration::from_millis(ms)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            name
+        })
+        .await
+        .unwrap();
+    }
+    drop(tx);
+
+    let results = ChannelStream::new(rx).buffer_unordered(2);
+    let got = record(start, results).await;
+    // `c` starts when `b` finishes, `d` when `c` does; `a` runs throughout.
+    check(
+        "buffer_unordered",
+        got,
+        &[(10, "b"), (30, "c"), (35, "d"), (50, "a")],
+    );
+    assert_eq!(peak.load(Ordering::SeqCst), 2);
+}
+
+async fn chunks_timeout() {
+    let start = Instant::now();
+    let events = vec![(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (100, 6)];
+    let events = [events, vec![(200, 7), (201, 8)]].concat();
+    let chunks =
+        timed(start, events).chunks_timeout(3, Duration::from_millis(10));
+    let got = record(start, chunks).await;
+    check(
+        "chunks_timeout",
+        got,
+        &[
+            // Full.
+            (2, vec![1, 2, 3]),
+            // Due 10 ms after its first item.
+            (13, vec![4, 5]),
+            (110, vec![6]),
+            // Cut short by the end of the stream.
+            (201, vec![7, 8]),
+        ],
+    );
+}
+
+async fn throttle() {
+    let start = Instant::now();
+    let (tx, rx) = mpsc::channel(16);
+    for i in 0..5 {
+        tx.send(i).await.unwrap();
+    }
+    drop(tx);
+    let throttled = ChannelStream::new(rx).throttle(Duration::from_millis(10));
+    let got = record(start, throttled).await;
+    // All five were ready at once; none is lost.
+    check(
+        "throttle",
+        got,
+        &[(0, 0), (10, 1), (20, 2), (30, 3), (40, 4)],
+    );
+}
+
+async fn merge() {
+    let start = Instant::now();
+    let left = timed(start, vec![(0, "l0"), (20, "l1"), (40, "l2")]);
+    let right = timed(start, vec![(10, "r0"), (30, "r1")]);
+    let got = record(start, left.merge(right)).await;
+    check(
+        "merge",
+        got,
+        &[(0, "l0"), (10, "r0"), (20, "l1"), (30, "r1"), (40, "l2")],
+    );
+
+    // A stream that always has an item ready does not starve the other one.
+    let (busy_tx, busy_rx) = mpsc::channel(16);
+    let (idle_tx, idle_rx) = mpsc::channel(16);
+    for _ in 0..10 {
+        busy_tx.send("busy").await.unwrap();
+    }
+    idle_tx.send("idle").await.unwrap();
+    let mut merged =
+        ChannelStream::new(busy_rx).merge(ChannelStream::new(idle_rx));
+    let first: Vec<_> = [merged.next().await, merged.next().await].into();
+    assert!(first.contains(&Some("idle")), "{:?}", first);
+}
+
+async fn debounce() {
+    let start = Instant::now();
+    let events = vec![(0, "h"), (5, "he"), (10, "hel"), (50, "hell")];
+    let events = [events, vec![(100, "hello"), (103, "hello!")]].concat();
+    let debounced = timed(start, events).debounce(Duration::from_millis(20));
+    let got = record(start, debounced).await;
+    check(
+        "debounce",
+        got,
+        // The last item comes out as soon as the stream ends.
+        &[(30, "hel"), (70, "hell"), (103, "hello!")],
+    );
+}
+
+// The clock is paused: whenever every task is waiting on a timer, Tokio jumps
+// straight to the next deadline. The checks run instantly and the timings are
+// exact.
+#[tokio::main(flavor = "current_thread", start_paused = true)]
+async fn main() {
+    map_filter().await;
+    intervals().await;
+    buffer_unordered().await;
+    chunks_timeout().await;
+    throttle().await;
+    merge().await;
+    debounce().await;
+}
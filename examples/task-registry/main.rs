@@ -0,0 +1,148 @@
+mod registry;
+
+use std::{panic, sync::Arc, time::Duration};
+
+use colored::Colorize;
+use tokio::{
+    process::Command,
+    signal::unix::{SignalKind, signal},
+    sync::{Notify, mpsc},
+    time::{self, sleep, timeout},
+};
+
+use registry::{KEEP_FINISHED, State, TaskInfo, dump, snapshot, spawn_named};
+
+fn find(tasks: &[TaskInfo], name: &str) -> TaskInfo {
+    tasks.iter().find(|t| t.name == name).unwrap().clone()
+}
+
+/// The hang from the `mpsc` section of the concurrency chapter: the
+/// pipeline keeps its own `tx`, so the receiver never sees the channel close.
+async fn forgotten_sender() -> anyhow::Result<()> {
+    let mut pipeline = spawn_named("pipeline", async {
+        let (tx, mut rx) = mpsc::channel(16);
+        for i in 0..4 {
+            let tx = tx.clone();
+            spawn_named(&format!("producer-{}", i), async move {
+                tx.send(i).await.unwrap();
+            });
+        }
+        // Missing: drop(tx);
+        let receiver = spawn_named("receiver", async move {
+            let mut sum = 0;
+            while let Some(value) = rx.recv().await {
+                sum += value;
+            }
+            sum
+        });
+        receiver.await.unwrap()
+    });
+
+    if timeout(Duration::from_millis(200), &mut pipeline)
+        .await
+        .is_err()
+    {
+        println!("{}\n{}", "pipeline is stuck:".red(), dump());
+    }
+
+    // The dump tells the story: every producer is done, but the receiver
+    // and the pipeline that awaits it are still idle.
+    let tasks = snapshot();
+    let pipeline_info = find(&tasks, "pipeline");
+    let receiver = find(&tasks, "receiver");
+    assert_eq!(pipeline_info.state, State::Idle);
+    assert_eq!(receiver.state, State::Idle);
+    assert_eq!(receiver.parent, Some(pipeline_info.id));
+    assert!(receiver.location.file().ends_with("main.rs"));
+    for i in 0..4 {
+        let producer = find(&tasks, &format!("producer-{}", i));
+        assert_eq!(producer.state, State::Completed);
+        assert_eq!(producer.parent, Some(pipeline_info.id));
+    }
+
+    // Aborting the pipeline drops its `tx`, which lets the receiver finish.
+    pipeline.abort();
+    sleep(Duration::from_millis(20)).await;
+    let tasks = snapshot();
+    assert_eq!(find(&tasks, "pipeline").state, State::Cancelled);
+    assert_eq!(find(&tasks, "receiver").state, State::Completed);
+    Ok(())
+}
+
+async fn panics() {
+    let crasher = spawn_named("crasher", async {
+        sleep(Duration::from_millis(5)).await;
+        panic!("registry: expected panic");
+    });
+    // The panic still reaches the `JoinHandle`.
+    assert!(crasher.await.unwrap_err().is_panic());
+    let crasher = find(&snapshot(), "crasher");
+    assert_eq!(crasher.state, State::Panicked);
+    assert_eq!(crasher.polls, 2);
+}
+
+/// Finished tasks are forgotten, oldest first, so the registry stays small.
+async fn bounded() {
+    let handles: Vec<_> = (0..KEEP_FINISHED * 4)
+        .map(|i| spawn_named(&format!("short-{}", i), async {}))
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    let tasks = snapshot();
+    let finished = tasks.iter().filter(|t| t.state.is_finished()).count();
+    assert_eq!(finished, KEEP_FINISHED);
+    assert!(tasks.iter().all(|t| t.name != "short-0"));
+    let last = format!("short-{}", KEEP_FINISHED * 4 - 1);
+    assert_eq!(find(&tasks, &last).state, State::Completed);
+    // The task that is still alive is kept, however old.
+    assert_eq!(find(&tasks, "sigusr1").state, State::Idle);
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let message = info.payload().downcast_ref::<&str>();
+        if message != Some(&"registry: expected panic") {
+            default_hook(info);
+        }
+    }));
+
+    // `kill -USR1 <pid>` prints the task tree, whatever the program is
+    // doing at the time.
+    let mut sigusr1 = signal(SignalKind::user_defined1())?;
+    let dumped = Arc::new(Notify::new());
+    spawn_named("sigusr1", {
+        let dumped = dumped.clone();
+        async move {
+            while sigusr1.recv().await.is_some() {
+                println!("{}\n{}", "SIGUSR1:".cyan(), dump());
+                dumped.notify_one();
+            }
+        }
+    });
+
+    // The checks wait on timers, which the paused clock makes instant and
+    // exact. It runs again for the signal: a paused clock would fire the
+    // timeout below while the runtime waits for the signal to arrive.
+    time::pause();
+    forgotten_sender().await?;
+    panics().await;
+    bounded().await;
+    time::resume();
+
+    let pid = std::process::id();
+    println!("sending SIGUSR1 to {}", pid);
+    let status = Command::new("kill")
+        .args(["-USR1", &pid.to_string()])
+        .status()
+        .await?;
+    assert!(status.success());
+    timeout(Duration::from_secs(2), dumped.notified()).await?;
+
+    let handler = find(&snapshot(), "sigusr1");
+    assert_eq!(handler.state, State::Idle);
+    assert!(handler.polls >= 2);
+    Ok(())
+}
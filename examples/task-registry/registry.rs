@@ -0,0 +1,230 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::{self, Write as _},
+    panic::{self, AssertUnwindSafe, Location},
+    pin::Pin,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(u64);
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Waiting to be woken, or queued to run.
+    Idle,
+    /// Inside `poll` right now.
+    Running,
+    Completed,
+    Panicked,
+    /// Dropped before it completed: aborted, or still pending when the
+    /// runtime shut down.
+    Cancelled,
+}
+
+impl State {
+    pub fn is_finished(self) -> bool {
+        !matches!(self, State::Idle | State::Running)
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            State::Idle => "idle".yellow(),
+            State::Running => "running".green(),
+            State::Completed => "completed".dimmed(),
+            State::Panicked => "panicked".red(),
+            State::Cancelled => "cancelled".dimmed(),
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    pub id: TaskId,
+    pub name: String,
+    pub location: &'static Location<'static>,
+    /// The task that spawned this one, if it was spawned by a named task.
+    pub parent: Option<TaskId>,
+    pub state: State,
+    pub polls: u64,
+    /// Time spent inside `poll`.
+    pub busy: Duration,
+    pub spawned_at: Instant,
+}
+
+/// How many finished tasks the registry remembers.
+pub const KEEP_FINISHED: usize = 64;
+
+#[derive(Default)]
+struct Registry {
+    tasks: BTreeMap<TaskId, TaskInfo>,
+    /// Finished tasks, oldest first. Only the last `KEEP_FINISHED` are kept,
+    /// so a process that keeps spawning tasks does not grow without bound.
+    finished: VecDeque<TaskId>,
+}
+
+/// Every named task that is still alive, and the most recently finished
+/// ones, so a dump shows what a hanging task is still waiting for and what
+/// just happened.
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(Mutex::default);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+tokio::task_local! {
+    /// The named task being polled, so tasks it spawns know their parent.
+    static CURRENT: TaskId;
+}
+
+fn update(id: TaskId, f: impl FnOnce(&mut TaskInfo)) {
+    let mut registry = REGISTRY.lock().unwrap();
+    let Some(info) = registry.tasks.get_mut(&id) else {
+        return;
+    };
+    let was_finished = info.state.is_finished();
+    f(info);
+    if info.state.is_finished() && !was_finished {
+        registry.finished.push_back(id);
+        if registry.finished.len() > KEEP_FINISHED {
+            let oldest = registry.finished.pop_front().unwrap();
+            registry.tasks.remove(&oldest);
+        }
+    }
+}
+
+/// `tokio::spawn`, with the task recorded in the registry under `name`.
+#[track_caller]
+pub fn spawn_named<F>(name: &str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let id = TaskId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    let info = TaskInfo {
+        id,
+        name: name.to_string(),
+        location: Location::caller(),
+        parent: current(),
+        state: State::Idle,
+        polls: 0,
+        busy: Duration::ZERO,
+        spawned_at: Instant::now(),
+    };
+    REGISTRY.lock().unwrap().tasks.insert(id, info);
+    tokio::spawn(CURRENT.scope(
+        id,
+        Tracked {
+            inner: Box::pin(future),
+            id,
+        },
+    ))
+}
+
+/// The named task running on this thread, if any.
+pub fn current() -> Option<TaskId> {
+    CURRENT.try_with(|id| *id).ok()
+}
+
+struct Tracked<F> {
+    inner: Pin<Box<F>>,
+    id: TaskId,
+}
+
+impl<F: Future> Future for Tracked<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let id = self.id;
+        update(id, |info| info.state = State::Running);
+        let start = Instant::now();
+        // Catch a panic to record it, then let it continue to the
+        // `JoinHandle` as usual.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            self.inner.as_mut().poll(cx)
+        }));
+        let elapsed = start.elapsed();
+        let state = match &result {
+            Ok(Poll::Ready(_)) => State::Completed,
+            Ok(Poll::Pending) => State::Idle,
+            Err(_) => State::Panicked,
+        };
+        update(id, |info| {
+            info.state = state;
+            info.polls += 1;
+            info.busy += elapsed;
+        });
+        result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+    }
+}
+
+impl<F> Drop for Tracked<F> {
+    fn drop(&mut self) {
+        update(self.id, |info| {
+            if !info.state.is_finished() {
+                info.state = State::Cancelled;
+            }
+        });
+    }
+}
+
+pub fn snapshot() -> Vec<TaskInfo> {
+    REGISTRY.lock().unwrap().tasks.values().cloned().collect()
+}
+
+/// The task tree: every task under the task that spawned it.
+pub fn dump() -> String {
+    let tasks = snapshot();
+    let ids: BTreeSet<TaskId> = tasks.iter().map(|t| t.id).collect();
+    let mut children: BTreeMap<Option<TaskId>, Vec<&TaskInfo>> =
+        BTreeMap::new();
+    for task in &tasks {
+        // A parent that finished long ago is forgotten; show its children
+        // at the top level.
+        let parent = task.parent.filter(|parent| ids.contains(parent));
+        children.entry(parent).or_default().push(task);
+    }
+
+    fn write_tree(
+        out: &mut String,
+        children: &BTreeMap<Option<TaskId>, Vec<&TaskInfo>>,
+        parent: Option<TaskId>,
+        depth: usize,
+    ) {
+        for task in children.get(&parent).into_iter().flatten() {
+            let _ = writeln!(
+                out,
+                "{:indent$}{} {} [{}] polls {}, busy {:.1?}, age {:.1?}, at {}",
+                "",
+                task.id,
+                task.name.bold(),
+                task.state,
+                task.polls,
+                task.busy,
+                task.spawned_at.elapsed(),
+                task.location,
+                indent = depth * 2
+            );
+            write_tree(out, children, Some(task.id), depth + 1);
+        }
+    }
+
+    let alive = tasks.iter().filter(|t| !t.state.is_finished()).count();
+    let mut out = format!("{} tasks, {} alive\n", tasks.len(), alive);
+    write_tree(&mut out, &children, None, 0);
+    out
+}
@@ -0,0 +1,180 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    task::{Context, Poll, Waker},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::wheel::{TimerQueue, TimerWheel};
+
+/// One tick of the wheel. `drive` counts on it being a millisecond.
+const TICK: Duration = Duration::from_millis(1);
+
+struct State {
+    wheel: TimerWheel<Waker>,
+    shutdown: bool,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    /// Signalled when a timer is added, so the driver can wake up earlier.
+    condvar: Condvar,
+    start: Instant,
+}
+
+impl Inner {
+    /// Ticks since the timer started, rounded down.
+    fn now(&self) -> u64 {
+        (self.start.elapsed().as_nanos() / TICK.as_nanos()) as u64
+    }
+
+    /// The first tick at or after `instant`. Rounding up means a sleep
+    /// never ends early.
+    fn tick_at(&self, instant: Instant) -> u64 {
+        let since = instant.saturating_duration_since(self.start);
+        since.as_nanos().div_ceil(TICK.as_nanos()) as u64
+    }
+}
+
+/// Handle to the timer, cheap to clone. Sleeps need the driver to be
+/// running: once the `Driver` is dropped, they never complete.
+#[derive(Clone)]
+pub struct Timer {
+    inner: Arc<Inner>,
+}
+
+/// Owns the background thread that fires the timers.
+pub struct Driver {
+    inner: Arc<Inner>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Starts the driver thread.
+pub fn start() -> (Timer, Driver) {
+    let inner = Arc::new(Inner {
+        state: Mutex::new(State {
+            wheel: TimerWheel::new(),
+            shutdown: false,
+        }),
+        condvar: Condvar::new(),
+        start: Instant::now(),
+    });
+    let thread = thread::spawn({
+        let inner = inner.clone();
+        move || drive(&inner)
+    });
+    let timer = Timer {
+        inner: inner.clone(),
+    };
+    let driver = Driver {
+        inner,
+        thread: Some(thread),
+    };
+    (timer, driver)
+}
+
+/// Fires expired timers, then sleeps until the next deadline or until a
+/// new timer is added.
+fn drive(inner: &Inner) {
+    let mut state = inner.state.lock().unwrap();
+    while !state.shutdown {
+        let fired = state.wheel.advance(inner.now());
+        if !fired.is_empty() {
+            // Wake outside the lock: a woken task may be polled right away,
+            // on another thread, and register a new timer.
+            drop(state);
+            for waker in fired {
+                waker.wake();
+            }
+            state = inner.state.lock().unwrap();
+            continue;
+        }
+        state = match state.wheel.next_expiration() {
+            Some(tick) => {
+                // In `u64` milliseconds, one per tick: a `u32` count of
+                // ticks would wrap for timers over 49 days away.
+                let deadline = inner.start + Duration::from_millis(tick);
+                let timeout =
+                    deadline.saturating_duration_since(Instant::now());
+                inner.condvar.wait_timeout(state, timeout).unwrap().0
+            }
+            None => inner.condvar.wait(state).unwrap(),
+        };
+    }
+}
+
+impl Drop for Driver {
+    fn drop(&mut self) {
+        self.inner.state.lock().unwrap().shutdown = true;
+        self.inner.condvar.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Timer {
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        self.sleep_until(Instant::now() + duration)
+    }
+
+    pub fn sleep_until(&self, deadline: Instant) -> Sleep {
+        Sleep {
+            timer: self.clone(),
+            deadline: self.inner.tick_at(deadline),
+            key: None,
+        }
+    }
+
+    /// How many sleeps are registered and waiting to fire.
+    pub fn pending(&self) -> usize {
+        self.inner.state.lock().unwrap().wheel.len()
+    }
+}
+
+/// A future that completes at a tick of the wheel.
+pub struct Sleep {
+    timer: Timer,
+    deadline: u64,
+    /// Set while the waker is registered in the wheel.
+    key: Option<u64>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = &mut *self;
+        let inner = &this.timer.inner;
+        let mut state = inner.state.lock().unwrap();
+        if inner.now() >= this.deadline {
+            if let Some(key) = this.key.take() {
+                state.wheel.remove(key);
+            }
+            return Poll::Ready(());
+        }
+
+        // Polled again before the deadline: keep the registration, but
+        // store the current waker.
+        if let Some(waker) = this.key.and_then(|key| state.wheel.get_mut(key)) {
+            waker.clone_from(cx.waker());
+            return Poll::Pending;
+        }
+        this.key = Some(state.wheel.insert(this.deadline, cx.waker().clone()));
+        drop(state);
+        // The driver may be waiting for a later deadline.
+        inner.condvar.notify_one();
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    /// Cancels the timer, so a sleep that lost a `select!` does not stay in
+    /// the wheel until its deadline.
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.timer.inner.state.lock().unwrap().wheel.remove(key);
+        }
+    }
+}
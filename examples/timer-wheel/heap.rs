@@ -0,0 +1,80 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::wheel::TimerQueue;
+
+/// The textbook alternative: a min-heap ordered by deadline. Inserting and
+/// expiring are O(log n). Cancelled timers stay in the heap until they reach
+/// the top, as removing from the middle of a heap is O(n).
+pub struct HeapTimer<T> {
+    heap: BinaryHeap<Reverse<(u64, u64)>>,
+    entries: HashMap<u64, T>,
+    next_key: u64,
+}
+
+impl<T> Default for HeapTimer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> HeapTimer<T> {
+    pub fn new() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            entries: HashMap::new(),
+            next_key: 0,
+        }
+    }
+
+    /// Drops cancelled timers from the top of the heap.
+    fn skip_cancelled(&mut self) {
+        while let Some(Reverse((_, key))) = self.heap.peek() {
+            if self.entries.contains_key(key) {
+                break;
+            }
+            self.heap.pop();
+        }
+    }
+}
+
+impl<T> TimerQueue<T> for HeapTimer<T> {
+    fn insert(&mut self, deadline: u64, value: T) -> u64 {
+        let key = self.next_key;
+        self.next_key += 1;
+        self.heap.push(Reverse((deadline, key)));
+        self.entries.insert(key, value);
+        key
+    }
+
+    fn remove(&mut self, key: u64) -> Option<T> {
+        self.entries.remove(&key)
+    }
+
+    fn advance(&mut self, now: u64) -> Vec<T> {
+        let mut fired = Vec::new();
+        while let Some(&Reverse((deadline, key))) = self.heap.peek() {
+            if deadline > now {
+                break;
+            }
+            self.heap.pop();
+            if let Some(value) = self.entries.remove(&key) {
+                fired.push(value);
+            }
+        }
+        self.skip_cancelled();
+        fired
+    }
+
+    fn next_expiration(&self) -> Option<u64> {
+        // A cancelled timer may still be on top, which only means the
+        // caller wakes up once for nothing.
+        self.heap.peek().map(|Reverse((deadline, _))| *deadline)
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
@@ -0,0 +1,203 @@
+mod driver;
+mod heap;
+mod wheel;
+
+use std::time::{Duration, Instant};
+
+use colored::Colorize;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use tokio::task::JoinSet;
+
+use driver::Timer;
+use heap::HeapTimer;
+use wheel::{TimerQueue, TimerWheel};
+
+/// Starting at tick `start`, inserts timers with random deadlines up to
+/// `max_delay` ahead, cancels some of them, and advances time in random
+/// steps. Checks that every timer fires exactly once, never early and never
+/// late, and in deadline order. Returns the deadlines in the order they
+/// fired.
+fn run_timers<Q: TimerQueue<u64>>(
+    queue: &mut Q,
+    seed: u64,
+    start: u64,
+    count: usize,
+    max_delay: u64,
+) -> Vec<u64> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    assert!(queue.advance(start).is_empty());
+    let mut keys = Vec::new();
+    let mut expected = 0;
+    for _ in 0..count {
+        let deadline = start + rng.random_range(0..max_delay);
+        keys.push((queue.insert(deadline, deadline), deadline));
+    }
+    for (key, deadline) in keys {
+        if rng.random_ratio(1, 4) {
+            assert_eq!(queue.remove(key), Some(deadline), "seed {}", seed);
+        } else {
+            expected += 1;
+        }
+    }
+    assert_eq!(queue.len(), expected, "seed {}", seed);
+
+    let mut now = start;
+    let mut fired = Vec::new();
+    while !queue.is_empty() {
+        let previous = now;
+        now += rng.random_range(1..max_delay / 50 + 2);
+        for deadline in queue.advance(now) {
+            assert!(deadline <= now, "fired early: seed {}", seed);
+            let due_before = deadline <= previous && previous > start;
+            assert!(!due_before, "fired late: seed {}", seed);
+            fired.push(deadline);
+        }
+    }
+    assert!(fired.is_sorted(), "out of order: seed {}", seed);
+    assert_eq!(fired.len(), expected, "seed {}", seed);
+    fired
+}
+
+fn ordering() {
+    let seed: u64 = rand::random();
+    println!("timer test seed: {}", seed);
+    for (case, max_delay) in [10, 64, 65, 4096, 5000, 1 << 20, 1 << 35]
+        .into_iter()
+        .enumerate()
+    {
+        let seed = seed.wrapping_add(case as u64);
+        let wheel =
+            run_timers(&mut TimerWheel::new(), seed, 0, 2000, max_delay);
+        let heap = run_timers(&mut HeapTimer::new(), seed, 0, 2000, max_delay);
+        assert_eq!(wheel, heap, "seed {}", seed);
+    }
+
+    // Deadlines past a 2^36-tick boundary wrap around in the top level, up
+    // to the longest delay the wheel supports.
+    for (case, start) in [(1 << 36) - 1, (1 << 36) - 5000, 3 << 36]
+        .into_iter()
+        .enumerate()
+    {
+        let seed = seed.wrapping_add(100 + case as u64);
+        let max_delay = (1 << 36) - 1;
+        let wheel =
+            run_timers(&mut TimerWheel::new(), seed, start, 2000, max_delay);
+        let heap =
+            run_timers(&mut HeapTimer::new(), seed, start, 2000, max_delay);
+        assert_eq!(wheel, heap, "seed {}", seed);
+    }
+    let mut wheel = TimerWheel::new();
+    wheel.advance((1 << 36) - 1);
+    wheel.insert((1 << 36) + 5, "across");
+    assert_eq!(wheel.next_expiration(), Some(1 << 36));
+    assert!(wheel.advance((1 << 36) + 4).is_empty());
+    assert_eq!(wheel.advance((1 << 36) + 5), ["across"]);
+
+    // Many timers: a million, spread over an hour of 1 ms ticks.
+    let fired =
+        run_timers(&mut TimerWheel::new(), seed, 0, 1_000_000, 3_600_000);
+    println!(
+        "{} {} timers fired in deadline order",
+        "wheel:".green(),
+        fired.len()
+    );
+
+    // A timer inserted in the past fires on the next advance.
+    let mut wheel = TimerWheel::new();
+    wheel.advance(100);
+    wheel.insert(50, "late");
+    assert_eq!(wheel.next_expiration(), Some(100));
+    assert_eq!(wheel.advance(100), ["late"]);
+}
+
+/// Times `count` inserts, cancelling every other timer, and expiring the
+/// rest by jumping from one expiration to the next, the way a driver would.
+fn bench<Q: TimerQueue<u64>>(mut queue: Q, count: usize) -> Duration {
+    let mut rng = StdRng::seed_from_u64(42);
+    let deadlines: Vec<u64> =
+        (0..count).map(|_| rng.random_range(1..60_000)).collect();
+
+    let start = Instant::now();
+    let keys: Vec<u64> = deadlines
+        .iter()
+        .map(|&deadline| queue.insert(deadline, deadline))
+        .collect();
+    for key in keys.iter().step_by(2) {
+        queue.remove(*key);
+    }
+    let mut fired = 0;
+    while let Some(next) = queue.next_expiration() {
+        fired += queue.advance(next).len();
+    }
+    let elapsed = start.elapsed();
+    assert_eq!(fired, count / 2);
+    elapsed
+}
+
+fn benchmark() {
+    for count in [10_000, 100_000] {
+        let wheel = bench(TimerWheel::new(), count);
+        let heap = bench(HeapTimer::new(), count);
+        println!(
+            "{} {:>7} timers: wheel {:>10.2?}, heap {:>10.2?}",
+            "bench:".green(),
+            count,
+            wheel,
+            heap
+        );
+    }
+}
+
+async fn sleeps(timer: &Timer) -> anyhow::Result<()> {
+    // Every sleep ends after its deadline, and not much later.
+    let mut tasks = JoinSet::new();
+    let mut rng = rand::rng();
+    let start = Instant::now();
+    for _ in 0..10_000 {
+        let delay = Duration::from_millis(rng.random_range(0..300));
+        let timer = timer.clone();
+        tasks.spawn(async move {
+            let deadline = Instant::now() + delay;
+            timer.sleep_until(deadline).await;
+            Instant::now().checked_duration_since(deadline)
+        });
+    }
+    let mut late = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        late.push(result?.expect("woke up before the deadline"));
+    }
+    let average = late.iter().sum::<Duration>() / late.len() as u32;
+    assert!(average < Duration::from_millis(20), "{:?}", average);
+    println!(
+        "{} 10000 concurrent sleeps done in {:?}, {:?} late on average",
+        "driver:".green(),
+        start.elapsed(),
+        average
+    );
+
+    // The sleep that loses the race is dropped, which removes it from the
+    // wheel right away instead of at its deadline.
+    tokio::select! {
+        _ = timer.sleep(Duration::from_millis(10)) => {}
+        _ = timer.sleep(Duration::from_secs(3600)) => unreachable!(),
+    }
+    assert_eq!(timer.pending(), 0);
+
+    let start = Instant::now();
+    timer.sleep(Duration::from_millis(50)).await;
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed >= Duration::from_millis(50)
+            && elapsed < Duration::from_millis(100)
+    );
+    println!("{} slept {:?} for 50ms", "driver:".green(), elapsed);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    ordering();
+    benchmark();
+    let (timer, _driver) = driver::start();
+    sleeps(&timer).await
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+/// Slots per level: 64, so one `u64` bitmap tracks which slots are in use.
+const SLOTS: usize = 64;
+const BITS: u32 = 6;
+/// Six levels of 64 slots cover 64^6 = 2^36 ticks, about 795 days at 1 ms a
+/// tick.
+const LEVELS: usize = 6;
+const MAX_DELAY: u64 = (1 << (BITS * LEVELS as u32)) - 1;
+
+/// The operations both timer implementations offer, so they can be
+/// compared with the same code. Time is counted in ticks.
+pub trait TimerQueue<T> {
+    /// Adds a timer and returns a key to cancel it with.
+    fn insert(&mut self, deadline: u64, value: T) -> u64;
+    /// Cancels a timer that has not fired yet.
+    fn remove(&mut self, key: u64) -> Option<T>;
+    /// Moves time forward to `now` and returns every timer that expired,
+    /// in deadline order.
+    fn advance(&mut self, now: u64) -> Vec<T>;
+    /// The tick of the earliest pending timer, if any.
+    fn next_expiration(&self) -> Option<u64>;
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+struct Level {
+    /// Keys of the timers in each slot. Cancelled keys stay here until the
+    /// slot is processed, so `remove` does not have to search the slot.
+    slots: Vec<Vec<u64>>,
+    /// Bit `i` is set when slot `i` is not empty.
+    occupied: u64,
+}
+
+/// A hierarchical timer wheel, as used by Tokio and the Linux kernel.
+///
+/// Level 0 has one slot per tick, level 1 one slot per 64 ticks, level 2
+/// one slot per 4096 ticks, and so on. A timer goes into the level that
+/// matches how far away it is. As time moves on, the timers of a higher
+/// level slot are moved down ("cascaded") to lower levels, until they reach
+/// level 0 and fire. Inserting and cancelling are O(1), however many timers
+/// there are.
+pub struct TimerWheel<T> {
+    now: u64,
+    levels: Vec<Level>,
+    /// Pending timers by key: `(deadline, value)`.
+    entries: HashMap<u64, (u64, T)>,
+    /// Timers inserted with a deadline that already passed.
+    expired: Vec<u64>,
+    next_key: u64,
+}
+
+impl<T> Default for TimerWheel<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TimerWheel<T> {
+    pub fn new() -> Self {
+        Self {
+            now: 0,
+            levels: (0..LEVELS)
+                .map(|_| Level {
+                    slots: (0..SLOTS).map(|_| Vec::new()).collect(),
+                    occupied: 0,
+                })
+                .collect(),
+            entries: HashMap::new(),
+            expired: Vec::new(),
+            next_key: 0,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: u64) -> Option<&mut T> {
+        self.entries.get_mut(&key).map(|(_, value)| value)
+    }
+
+    /// The level is given by the highest bit in which the deadline differs
+    /// from now: a deadline in the same 64-tick block goes to level 0, one
+    /// in the same 4096-tick block to level 1, and so on.
+    ///
+    /// A deadline past the next 2^36-tick boundary differs in a bit above
+    /// the top level, even when it is less than `MAX_DELAY` away. It goes to
+    /// the top level, where its slot wraps around, as in Tokio's `level_for`.
+    fn place(&mut self, key: u64, deadline: u64) {
+        if deadline <= self.now {
+            self.expired.push(key);
+            return;
+        }
+        let masked =
+            ((deadline ^ self.now) | (SLOTS as u64 - 1)).min(MAX_DELAY);
+        let significant = 63 - masked.leading_zeros();
+        let level = (significant / BITS) as usize;
+        let slot = ((deadline >> (BITS * level as u32)) as usize) % SLOTS;
+        let level = &mut self.levels[level];
+        level.slots[slot].push(key);
+        level.occupied |= 1 << slot;
+    }
+
+    /// The lowest level with a slot in use, the slot, and the tick at which
+    /// that slot starts. Every timer in a lower level expires before any
+    /// timer in a higher one, so the first level with a slot wins.
+    fn next_slot(&self) -> Option<(usize, usize, u64)> {
+        self.levels.iter().enumerate().find_map(|(index, level)| {
+            let shift = BITS * index as u32;
+            let current = ((self.now >> shift) as usize) % SLOTS;
+            let span = 1 << (shift + BITS);
+            let block = self.now & !(span - 1);
+            if index < LEVELS - 1 {
+                // Slots before the current one were already processed.
+                let ahead = level.occupied >> current;
+                if ahead == 0 {
+                    return None;
+                }
+                let slot = current + ahead.trailing_zeros() as usize;
+                let start = block + ((slot as u64) << shift);
+                return Some((index, slot, start.max(self.now)));
+            }
+            // In the top level, the slots up to the current one hold timers
+            // that wrapped around into the next 2^36-tick block, so they come
+            // after the slots past the current one.
+            let next = (current + 1) % SLOTS;
+            let ahead = level.occupied.rotate_right(next as u32);
+            if ahead == 0 {
+                return None;
+            }
+            let slot = (next + ahead.trailing_zeros() as usize) % SLOTS;
+            let mut start = block + ((slot as u64) << shift);
+            if slot <= current {
+                start += span;
+            }
+            Some((index, slot, start.max(self.now)))
+        })
+    }
+}
+
+impl<T> TimerQueue<T> for TimerWheel<T> {
+    fn insert(&mut self, deadline: u64, value: T) -> u64 {
+        let deadline = deadline.min(self.now + MAX_DELAY);
+        let key = self.next_key;
+        self.next_key += 1;
+        self.entries.insert(key, (deadline, value));
+        self.place(key, deadline);
+        key
+    }
+
+    fn remove(&mut self, key: u64) -> Option<T> {
+        self.entries.remove(&key).map(|(_, value)| value)
+    }
+
+    fn advance(&mut self, now: u64) -> Vec<T> {
+        let mut fired: Vec<T> = std::mem::take(&mut self.expired)
+            .into_iter()
+            .filter_map(|key| self.entries.remove(&key))
+            .map(|(_, value)| value)
+            .collect();
+
+        while let Some((level, slot, start)) = self.next_slot() {
+            if start > now {
+                break;
+            }
+            self.now = start;
+            let keys = std::mem::take(&mut self.levels[level].slots[slot]);
+            self.levels[level].occupied &= !(1 << slot);
+            for key in keys {
+                // Skip timers that were cancelled.
+                let Some(&(deadline, _)) = self.entries.get(&key) else {
+                    continue;
+                };
+                if deadline <= self.now {
+                    let (_, value) = self.entries.remove(&key).unwrap();
+                    fired.push(value);
+                } else {
+                    // Cascade: now that time is closer, the timer belongs
+                    // to a lower level.
+                    self.place(key, deadline);
+                }
+            }
+        }
+        self.now = self.now.max(now);
+        fired
+    }
+
+    fn next_expiration(&self) -> Option<u64> {
+        if !self.expired.is_empty() {
+            return Some(self.now);
+        }
+        self.next_slot().map(|(_, _, start)| start)
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
@@ -0,0 +1,460 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, JoinHandle as ThreadHandle, Thread},
+};
+
+use rand::Rng;
+
+/// Local queues are bounded, like Tokio's. When one is full, half of it
+/// moves to the injector, where every worker can get at it.
+const LOCAL_CAPACITY: usize = 256;
+/// How many times in a row a worker may run the task in its LIFO slot.
+/// Without a limit, two tasks that keep waking each other would starve the
+/// rest of the queue.
+const LIFO_LIMIT: usize = 3;
+/// Check the injector first every so many tasks, so tasks waiting there
+/// are not starved by busy local queues.
+const INJECTOR_INTERVAL: usize = 61;
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+struct Task {
+    future: Mutex<Option<BoxFuture>>,
+    /// Already in a queue or a LIFO slot, so waking it again does nothing.
+    scheduled: AtomicBool,
+    shared: Arc<Shared>,
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.scheduled.swap(true, Ordering::AcqRel) {
+            self.shared.schedule(self.clone());
+        }
+    }
+}
+
+impl Task {
+    fn run(self: Arc<Self>) {
+        self.scheduled.store(false, Ordering::Release);
+        let mut slot = self.future.lock().unwrap();
+        if let Some(future) = slot.as_mut() {
+            let waker = Waker::from(self.clone());
+            let mut cx = Context::from_waker(&waker);
+            if future.as_mut().poll(&mut cx).is_ready() {
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// Counters for one worker.
+#[derive(Debug, Default)]
+struct Counters {
+    polled: AtomicUsize,
+    lifo: AtomicUsize,
+    stolen: AtomicUsize,
+    parked: AtomicUsize,
+}
+
+/// A snapshot of what one worker did.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WorkerStats {
+    /// Tasks polled.
+    pub polled: usize,
+    /// Of these, tasks taken from the LIFO slot.
+    pub lifo: usize,
+    /// Tasks stolen from other workers.
+    pub stolen: usize,
+    /// Times the worker went to sleep for lack of work.
+    pub parked: usize,
+}
+
+struct Worker {
+    /// Tasks for this worker. The owner pops from the front; thieves take
+    /// half from the back.
+    local: Mutex<VecDeque<Arc<Task>>>,
+    counters: Counters,
+}
+
+struct Shared {
+    workers: Vec<Worker>,
+    /// Tasks spawned or woken from outside the workers.
+    injector: Mutex<VecDeque<Arc<Task>>>,
+    /// Workers that are parked, or about to park.
+    idle: Mutex<Vec<usize>>,
+    num_idle: AtomicUsize,
+    threads: Mutex<Vec<Thread>>,
+    shutdown: AtomicBool,
+}
+
+/// The worker running on this thread: the executor it belongs to, its
+/// index, and its LIFO slot.
+struct Current {
+    shared: Arc<Shared>,
+    index: usize,
+    lifo: Option<Arc<Task>>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Current>> = const { RefCell::new(None) };
+}
+
+impl Shared {
+    /// Where a woken task goes. On a worker of this executor, into the
+    /// worker's LIFO slot: the task was most likely woken by the task that
+    /// just ran, and running it next, on the same thread, finds its data
+    /// still in the CPU cache. From anywhere else, into the injector.
+    fn schedule(self: &Arc<Self>, task: Arc<Task>) {
+        let mut task = Some(task);
+        let queued = CURRENT.with(|current| match &mut *current.borrow_mut() {
+            Some(current) if Arc::ptr_eq(&current.shared, self) => {
+                // The task it replaces goes to the local queue.
+                let previous = current.lifo.replace(task.take().unwrap());
+                previous
+                    .map(|previous| self.push_local(current.index, previous))
+                    .is_some()
+            }
+            _ => false,
+        });
+        let injected = task.is_some();
+        if let Some(task) = task {
+            self.injector.lock().unwrap().push_back(task);
+        }
+        // A task in a LIFO slot cannot be stolen, so it is no reason to
+        // wake another worker. A task in a queue is.
+        if queued || injected {
+            self.notify_one();
+        }
+    }
+
+    fn push_local(&self, index: usize, task: Arc<Task>) {
+        let mut local = self.workers[index].local.lock().unwrap();
+        if local.len() < LOCAL_CAPACITY {
+            local.push_back(task);
+            return;
+        }
+        let half = local.len() / 2;
+        let mut injector = self.injector.lock().unwrap();
+        injector.extend(local.drain(half..));
+        injector.push_back(task);
+    }
+
+    /// Wakes one parked worker, if there is one.
+    fn notify_one(&self) {
+        // Cheap check first: on a busy executor nobody is idle.
+        if self.num_idle.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let index = self.idle.lock().unwrap().pop();
+        if let Some(index) = index {
+            self.num_idle.fetch_sub(1, Ordering::SeqCst);
+            self.threads.lock().unwrap()[index].unpark();
+        }
+    }
+
+    fn has_work(&self) -> bool {
+        !self.injector.lock().unwrap().is_empty()
+            || self
+                .workers
+                .iter()
+                .any(|worker| !worker.local.lock().unwrap().is_empty())
+    }
+
+    /// Moves a batch from the injector to the local queue of `index`, and
+    /// returns the first task.
+    fn take_injected(&self, index: usize) -> Option<Arc<Task>> {
+        let mut injector = self.injector.lock().unwrap();
+        let task = injector.pop_front()?;
+        // Take a fair share, so the other workers get some too.
+        let batch =
+            (injector.len() / self.workers.len()).min(LOCAL_CAPACITY / 2);
+        let mut local = self.workers[index].local.lock().unwrap();
+        local.extend(injector.drain(..batch));
+        Some(task)
+    }
+
+    /// Steals half of the local queue of another worker, starting at a
+    /// random one so thieves spread out over their victims.
+    fn steal(&self, index: usize) -> Option<Arc<Task>> {
+        let count = self.workers.len();
+        let start = rand::rng().random_range(0..count);
+        for offset in 0..count {
+            let victim = (start + offset) % count;
+            if victim == index {
+                continue;
+            }
+            let mut stolen = {
+                let mut queue = self.workers[victim].local.lock().unwrap();
+                let half = queue.len().div_ceil(2);
+                let at = queue.len() - half;
+                queue.split_off(at)
+            };
+            let Some(task) = stolen.pop_front() else {
+                continue;
+            };
+            let counters = &self.workers[index].counters;
+            counters
+                .stolen
+                .fetch_add(stolen.len() + 1, Ordering::Relaxed);
+            self.workers[index].local.lock().unwrap().extend(stolen);
+            return Some(task);
+        }
+        None
+    }
+}
+
+/// The main loop of a worker thread.
+fn run_worker(shared: Arc<Shared>, index: usize) {
+    CURRENT.with(|current| {
+        *current.borrow_mut() = Some(Current {
+            shared: shared.clone(),
+            index,
+            lifo: None,
+        });
+    });
+    let worker = &shared.workers[index];
+    let mut ticks = 0;
+    let mut lifo_streak = 0;
+
+    while !shared.shutdown.load(Ordering::Acquire) {
+        ticks += 1;
+        let lifo = CURRENT.with(|current| {
+            current.borrow_mut().as_mut().and_then(|c| c.lifo.take())
+        });
+        let task = match lifo {
+            Some(task) if lifo_streak < LIFO_LIMIT => {
+                lifo_streak += 1;
+                worker.counters.lifo.fetch_add(1, Ordering::Relaxed);
+                Some(task)
+            }
+            other => {
+                lifo_streak = 0;
+                if let Some(task) = other {
+                    shared.push_local(index, task);
+                }
+                let injected_first = ticks % INJECTOR_INTERVAL == 0;
+                injected_first
+                    .then(|| shared.take_injected(index))
+                    .flatten()
+                    .or_else(|| worker.local.lock().unwrap().pop_front())
+                    .or_else(|| shared.take_injected(index))
+                    .or_else(|| shared.steal(index))
+            }
+        };
+
+        match task {
+            Some(task) => {
+                worker.counters.polled.fetch_add(1, Ordering::Relaxed);
+                task.run();
+            }
+            None => park(&shared, index),
+        }
+    }
+
+    // Drop our reference to the executor, and the task in the LIFO slot.
+    CURRENT.with(|current| current.borrow_mut().take());
+}
+
+fn park(shared: &Shared, index: usize) {
+    shared.idle.lock().unwrap().push(index);
+    shared.num_idle.fetch_add(1, Ordering::SeqCst);
+    // Look once more after announcing that we are idle. A task pushed
+    // before that was pushed by someone who saw no idle worker, so nobody
+    // would wake us for it.
+    if !shared.has_work() && !shared.shutdown.load(Ordering::Acquire) {
+        shared.workers[index]
+            .counters
+            .parked
+            .fetch_add(1, Ordering::Relaxed);
+        thread::park();
+    }
+    // Whoever unparked us already took us off the list. After a spurious
+    // wake-up, or if we never parked, we take ourselves off.
+    let mut idle = shared.idle.lock().unwrap();
+    if let Some(position) = idle.iter().position(|&i| i == index) {
+        idle.swap_remove(position);
+        shared.num_idle.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct JoinState<T> {
+    output: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// Resolves to the output of a spawned task.
+pub struct JoinHandle<T> {
+    state: Arc<Mutex<JoinState<T>>>,
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock().unwrap();
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+fn spawn_on<F>(shared: &Arc<Shared>, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let state = Arc::new(Mutex::new(JoinState {
+        output: None,
+        waker: None,
+    }));
+    let task_state = state.clone();
+    let task = Arc::new(Task {
+        future: Mutex::new(Some(Box::pin(async move {
+            let output = future.await;
+            let mut state = task_state.lock().unwrap();
+            state.output = Some(output);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }))),
+        scheduled: AtomicBool::new(false),
+        shared: shared.clone(),
+    });
+    task.wake_by_ref();
+    JoinHandle { state }
+}
+
+/// Spawns a task from inside another task.
+///
+/// # Panics
+///
+/// When not called on a worker thread.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let shared = CURRENT
+        .with(|current| current.borrow().as_ref().map(|c| c.shared.clone()))
+        .expect("spawn called outside of a worker thread");
+    spawn_on(&shared, future)
+}
+
+/// Wakes the thread blocked in `block_on`.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// A work-stealing executor with a fixed number of worker threads.
+pub struct Executor {
+    shared: Arc<Shared>,
+    handles: Vec<ThreadHandle<()>>,
+}
+
+impl Executor {
+    pub fn new(workers: usize) -> Self {
+        assert!(workers > 0, "an executor needs at least one worker");
+        let shared = Arc::new(Shared {
+            workers: (0..workers)
+                .map(|_| Worker {
+                    local: Mutex::new(VecDeque::new()),
+                    counters: Counters::default(),
+                })
+                .collect(),
+            injector: Mutex::new(VecDeque::new()),
+            idle: Mutex::new(Vec::new()),
+            num_idle: AtomicUsize::new(0),
+            threads: Mutex::new(Vec::new()),
+            shutdown: AtomicBool::new(false),
+        });
+        // Hold the lock while starting the threads, so no worker can try to
+        // unpark another before all of them are in the list.
+        let mut threads = shared.threads.lock().unwrap();
+        let handles: Vec<_> = (0..workers)
+            .map(|index| {
+                let shared = shared.clone();
+                thread::Builder::new()
+                    .name(format!("worker-{}", index))
+                    .spawn(move || run_worker(shared, index))
+                    .unwrap()
+            })
+            .collect();
+        threads.extend(handles.iter().map(|handle| handle.thread().clone()));
+        drop(threads);
+        Self { shared, handles }
+    }
+
+    /// Spawns a task from outside the executor. It goes to the injector.
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        spawn_on(&self.shared, future)
+    }
+
+    /// Blocks the current thread until `future` completes. The future runs
+    /// on this thread; the tasks it spawns run on the workers.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    pub fn stats(&self) -> Vec<WorkerStats> {
+        self.shared
+            .workers
+            .iter()
+            .map(|worker| WorkerStats {
+                polled: worker.counters.polled.load(Ordering::Relaxed),
+                lifo: worker.counters.lifo.load(Ordering::Relaxed),
+                stolen: worker.counters.stolen.load(Ordering::Relaxed),
+                parked: worker.counters.parked.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+impl Drop for Executor {
+    /// Stops the workers and drops the tasks that did not complete.
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::Release);
+        for thread in self.shared.threads.lock().unwrap().iter() {
+            thread.unpark();
+        }
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+        // Tasks hold a reference to `Shared`: clear the queues to break
+        // the cycle.
+        self.shared.injector.lock().unwrap().clear();
+        for worker in &self.shared.workers {
+            worker.local.lock().unwrap().clear();
+        }
+    }
+}
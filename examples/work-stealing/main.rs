@@ -0,0 +1,229 @@
+mod executor;
+
+use std::{
+    collections::HashSet,
+    pin::Pin,
+    thread,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+
+use executor::{Executor, WorkerStats, spawn};
+
+/// Below this, `par_fib` computes directly instead of spawning.
+const THRESHOLD: usize = 15;
+
+/// The CPU-bound workload from `task-management-joinset-blocking.rs`.
+fn fib(v: usize) -> usize {
+    match v {
+        0 => 0,
+        1 => 1,
+        _ => fib(v - 1) + fib(v - 2),
+    }
+}
+
+fn fib_iterative(v: usize) -> usize {
+    (0..v).fold((0, 1), |(a, b), _| (b, a + b)).0
+}
+
+/// Fibonacci with a task per branch: one big task becomes many small ones,
+/// all spawned on the same worker. The other workers only get a share by
+/// stealing.
+fn par_fib(n: usize) -> Pin<Box<dyn Future<Output = usize> + Send>> {
+    Box::pin(async move {
+        if n < THRESHOLD {
+            return fib(n);
+        }
+        let left = spawn(par_fib(n - 1));
+        let right = par_fib(n - 2).await;
+        left.await + right
+    })
+}
+
+/// The same on Tokio.
+fn tokio_par_fib(n: usize) -> Pin<Box<dyn Future<Output = usize> + Send>> {
+    Box::pin(async move {
+        if n < THRESHOLD {
+            return fib(n);
+        }
+        let left = tokio::spawn(tokio_par_fib(n - 1));
+        let right = tokio_par_fib(n - 2).await;
+        left.await.unwrap() + right
+    })
+}
+
+/// How many tasks `par_fib(n)` spawns.
+fn par_fib_tasks(n: usize) -> usize {
+    if n < THRESHOLD {
+        0
+    } else {
+        1 + par_fib_tasks(n - 1) + par_fib_tasks(n - 2)
+    }
+}
+
+fn print_stats(stats: &[WorkerStats]) {
+    for (index, worker) in stats.iter().enumerate() {
+        println!(
+            "  worker {}: polled {:>6}, lifo {:>6}, stolen {:>6}, parked {:>4}",
+            index, worker.polled, worker.lifo, worker.stolen, worker.parked
+        );
+    }
+}
+
+fn checks() {
+    let executor = Executor::new(4);
+
+    // Independent tasks spawned from outside go through the injector.
+    let results = executor.block_on(async {
+        let handles: Vec<_> = (0..=25)
+            .map(|value| executor.spawn(async move { (value, fib(value)) }))
+            .collect();
+        let mut results = Vec::new();
+        for handle in handles {
+            results.push(handle.await);
+        }
+        results
+    });
+    for (value, result) in results {
+        assert_eq!(result, fib_iterative(value));
+    }
+
+    // Tasks spawned by a task land on its worker, so the idle workers have
+    // to steal them. The blocking sleep stands in for CPU work and keeps
+    // the worker busy, even on a machine with a single core.
+    let threads = executor.block_on(executor.spawn(async {
+        let handles: Vec<_> = (0..64)
+            .map(|_| {
+                spawn(async {
+                    thread::sleep(Duration::from_millis(2));
+                    thread::current().name().unwrap().to_string()
+                })
+            })
+            .collect();
+        let mut threads = HashSet::new();
+        for handle in handles {
+            threads.insert(handle.await);
+        }
+        threads
+    }));
+    let stolen: usize = executor.stats().iter().map(|w| w.stolen).sum();
+    assert!(stolen > 0, "no tasks were stolen");
+    assert!(threads.len() > 1);
+    println!(
+        "{} 64 tasks spawned on one worker ran on {} workers, {} stolen",
+        "stealing:".green(),
+        threads.len(),
+        stolen
+    );
+
+    let n = 26;
+    let result = executor.block_on(executor.spawn(par_fib(n)));
+    assert_eq!(result, fib_iterative(n));
+
+    // A task that awaits its child is woken by the child, on the same
+    // worker, and runs next from the LIFO slot.
+    executor.block_on(executor.spawn(async {
+        for i in 0..1000 {
+            assert_eq!(spawn(async move { i * 2 }).await, i * 2);
+        }
+    }));
+    let lifo: usize = executor.stats().iter().map(|worker| worker.lifo).sum();
+    assert!(lifo >= 1000, "LIFO slot used {} times", lifo);
+
+    // Idle workers park instead of spinning: nothing is polled while there
+    // is no work.
+    thread::sleep(Duration::from_millis(50));
+    let before = executor.stats();
+    thread::sleep(Duration::from_millis(100));
+    let after = executor.stats();
+    for (before, after) in before.iter().zip(&after) {
+        assert_eq!(before.polled, after.polled);
+        assert!(after.parked > 0);
+    }
+    print_stats(&after);
+    println!("{}", "work-stealing executor ok".green());
+}
+
+/// Runs both workloads on our executor and on Tokio, with the same number
+/// of worker threads, and reports throughput.
+fn compare(max: usize) {
+    let workers = thread::available_parallelism().map_or(4, |n| n.get());
+    println!(
+        "{} workers, flat fib(0..={}), par_fib({})",
+        workers, max, max
+    );
+
+    let executor = Executor::new(workers);
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(workers)
+        .build()
+        .unwrap();
+
+    let flat_tasks = max + 1;
+    let start = Instant::now();
+    let ours: usize = executor.block_on(async {
+        let handles: Vec<_> = (0..=max)
+            .map(|value| executor.spawn(async move { fib(value) }))
+            .collect();
+        let mut sum = 0;
+        for handle in handles {
+            sum += handle.await;
+        }
+        sum
+    });
+    let ours_flat = start.elapsed();
+
+    let start = Instant::now();
+    let theirs: usize = runtime.block_on(async {
+        let handles: Vec<_> = (0..=max)
+            .map(|value| tokio::spawn(async move { fib(value) }))
+            .collect();
+        let mut sum = 0;
+        for handle in handles {
+            sum += handle.await.unwrap();
+        }
+        sum
+    });
+    let tokio_flat = start.elapsed();
+    assert_eq!(ours, theirs);
+
+    let nested_tasks = par_fib_tasks(max);
+    let start = Instant::now();
+    let ours = executor.block_on(executor.spawn(par_fib(max)));
+    let ours_nested = start.elapsed();
+    let start = Instant::now();
+    let theirs = runtime
+        .block_on(async { tokio::spawn(tokio_par_fib(max)).await.unwrap() });
+    let tokio_nested = start.elapsed();
+    assert_eq!(ours, theirs);
+
+    let report = |name: &str, tasks: usize, ours: Duration, tokio: Duration| {
+        let rate = |elapsed: Duration| tasks as f64 / elapsed.as_secs_f64();
+        println!(
+            "{} {:>7} tasks: ours {:>10.2?} ({:>9.0} tasks/s), \
+             tokio {:>10.2?} ({:>9.0} tasks/s)",
+            name.green(),
+            tasks,
+            ours,
+            rate(ours),
+            tokio,
+            rate(tokio)
+        );
+    };
+    report("flat:  ", flat_tasks, ours_flat, tokio_flat);
+    report("nested:", nested_tasks, ours_nested, tokio_nested);
+    print_stats(&executor.stats());
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        // cargo run --release --example work-stealing -- compare 35
+        Some("compare") => {
+            let max = args.next().map_or(32, |max| max.parse().unwrap());
+            compare(max);
+        }
+        _ => checks(),
+    }
+}